@@ -1,47 +1,624 @@
+use std::{
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use token_helper::{claims::Confirmation, mtls::certificate_thumbprint};
+use uuid::Uuid;
+
 use crate::{
-    api::{errors::*, model::UserCredentials},
+    api::{
+        errors::*,
+        model::{
+            ClientAuthentication, ClientCertificate, DpopRequest, ExchangedToken,
+            IntrospectionRequest, IntrospectionResponse, IssuedTokens, LoginCredentials,
+            RefreshRequest, RevocationRequest, TokenConfirmation, TokenExchangeRequest,
+            UserCredentials,
+        },
+        repository::{
+            DatabaseRepository, LoginApplication, MigrationStatus, NewRefreshToken, Repository,
+            TokenRepository, UserRepository,
+        },
+    },
     util::{
         database::{
-            connection::get_database_connection,
-            user_email::{
-                get_user_credentials,
-                register_new_user_email_password as reg_new_user_email_password,
+            connection::{create_database_repository, with_repository},
+            migrations,
+        },
+        security::{
+            dpop::verify_dpop_proof,
+            pairwise_subject::PairwiseSubjectStore,
+            password_hasher::{argon2::Argon2Hasher, pool::get_hashing_pool},
+            refresh_token::{generate_refresh_token, hash_refresh_token},
+            revocation::RevocationStore,
+            token_issuer::{
+                get_token_issuer_config, issue_access_token, issue_exchanged_token,
+                pairwise_subject_id, validation_options, TokenIssuerConfig,
             },
         },
-        security::password_hasher::{argon2::Argon2Hasher, PasswordHasher},
     },
 };
 
-pub fn login(credentials: UserCredentials) -> Result<String, ErrorDetails> {
-    let connection = &mut get_database_connection()?;
-    let user = get_user_credentials(connection, credentials.email)?;
+/// the grant type of the token exchange requests (RFC 8693)
+pub const TOKEN_EXCHANGE_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:token-exchange";
+/// the only token type accepted and issued by the token exchange
+pub const ACCESS_TOKEN_TYPE: &str = "urn:ietf:params:oauth:token-type:access_token";
+
+/// resolves the token keys and settings of the configuration and starts the password
+/// hashing threads, called at startup so an invalid configuration stops the server
+/// before serving requests
+pub fn load_configuration() -> Result<(), ErrorDetails> {
+    get_token_issuer_config()?;
+    get_hashing_pool()?;
+    Ok(())
+}
+
+/// creates the repository of the database of `DATABASE_URL`,
+/// called once at startup so its connection pool is shared by the requests
+pub fn database_repository() -> Result<DatabaseRepository, ErrorDetails> {
+    create_database_repository()
+}
+
+/// applies the pending migrations of the database when `DATABASE_MIGRATE_ON_START` is true,
+/// called at startup before serving the requests, returns the names of the applied migrations
+pub fn migrate_on_start(repository: &DatabaseRepository) -> Result<Vec<String>, ErrorDetails> {
+    match migrations::migrate_on_start()? {
+        true => migrations::run_pending_migrations(repository),
+        false => Ok(Vec::new()),
+    }
+}
+
+/// the migrations built in the server and whether they are applied to the database
+pub fn migration_status(
+    repository: &DatabaseRepository,
+) -> Result<Vec<MigrationStatus>, ErrorDetails> {
+    migrations::migration_status(repository)
+}
 
-    match <Argon2Hasher as PasswordHasher>::verify_password(
-        credentials.password.as_bytes(),
-        user.password_hash.as_bytes(),
-    ) {
-        Ok(_) => {
-            //create a new jwt token
+/// logs an user in, with a DPoP proof or a client certificate
+/// the issued tokens are bound to its key
+pub async fn login<R: Repository>(
+    repository: &R,
+    credentials: LoginCredentials<'_>,
+    dpop: Option<DpopRequest<'_>>,
+    certificate: Option<ClientCertificate<'_>>,
+) -> Result<IssuedTokens, ErrorDetails> {
+    let application_id = Uuid::parse_str(credentials.application_id)
+        .map_err(|e| ERR_INVALID_DATA.with_internal_error(e.to_string()))?;
+    let config = get_token_issuer_config()?;
+    let dpop_jkt = match &dpop {
+        Some(dpop) => Some(verify_dpop_proof(config, dpop)?),
+        None => None,
+    };
+    let x5t_s256 = certificate.map(|certificate| certificate_thumbprint(certificate.der));
+    let email = credentials.email.to_string();
+    let user = with_repository(repository, move |repository| {
+        repository.get_user_credentials(&email)
+    })
+    .await?;
+    get_hashing_pool()?
+        .verify_password::<Argon2Hasher>(credentials.password, user.password_hash.clone())
+        .await?
+        .map_err(|e| ERR_AUTHENTICATION_FAILED.with_internal_error(e))?;
 
-            return Ok("".to_string());
+    with_repository(repository, move |repository| {
+        // create a new jwt token for the requesting application
+        let application = repository.get_login_application(&application_id)?;
+        check_client_certificate(&application, x5t_s256.as_deref())?;
+        let auth_time = SystemTime::now();
+        let subject_id = token_subject_id(
+            repository,
+            config,
+            &user.user_id,
+            &application_id,
+            &application,
+        )?;
+        let access_token = issue_access_token(
+            config,
+            &subject_id,
+            &application.audience,
+            auth_time,
+            Confirmation {
+                jkt: dpop_jkt.clone(),
+                x5t_s256: x5t_s256.clone(),
+            },
+        )?;
+        // every login starts a new refresh token family
+        let refresh_token = generate_refresh_token();
+        repository.insert_refresh_token(NewRefreshToken {
+            token_hash: &hash_refresh_token(&refresh_token),
+            family_id: &Uuid::new_v4(),
+            user_id: &user.user_id,
+            application_id: &application_id,
+            expires_at: SystemTime::now() + config.refresh_lifetime,
+            auth_time,
+            dpop_jkt: dpop_jkt.as_deref(),
+            certificate_thumbprint: x5t_s256.as_deref(),
+        })?;
+        Ok(issued_tokens(
+            config,
+            access_token,
+            refresh_token,
+            dpop_jkt.is_some(),
+        ))
+    })
+    .await
+}
+
+/// exchanges a refresh token for a new access token
+/// the refresh token is rotated, using an already rotated token again
+/// revokes every token of its family,
+/// a family bound to a DPoP key can only be refreshed with a proof signed by that key,
+/// a family bound to a client certificate over a connection with the same certificate
+pub async fn refresh<R: Repository>(
+    repository: &R,
+    request: RefreshRequest<'_>,
+    dpop: Option<DpopRequest<'_>>,
+    certificate: Option<ClientCertificate<'_>>,
+) -> Result<IssuedTokens, ErrorDetails> {
+    let config = get_token_issuer_config()?;
+    let dpop_jkt = match &dpop {
+        Some(dpop) => Some(verify_dpop_proof(config, dpop)?),
+        None => None,
+    };
+    let x5t_s256 = certificate.map(|certificate| certificate_thumbprint(certificate.der));
+    let token_hash = hash_refresh_token(request.refresh_token);
+    with_repository(repository, move |repository| {
+        let record = repository.get_refresh_token(&token_hash)?;
+        if record.revoked {
+            return Err(ERR_INVALID_REFRESH_TOKEN
+                .with_internal_error("the refresh token was revoked".to_string()));
         }
-        Err(e) => {
-            return Err(ERR_AUTHENTICATION_FAILED.with_internal_error(e.to_string()));
+        if record.used {
+            return Err(reuse_detected(repository, &record.family_id));
         }
+        if record.expires_at < SystemTime::now() {
+            return Err(ERR_INVALID_REFRESH_TOKEN
+                .with_internal_error("the refresh token expired".to_string()));
+        }
+
+        if record.dpop_jkt.is_some() && record.dpop_jkt != dpop_jkt {
+            return Err(ERR_INVALID_DPOP_PROOF.with_internal_error(
+                "the refresh token is bound to another DPoP key".to_string(),
+            ));
+        }
+        if record.certificate_thumbprint.is_some() && record.certificate_thumbprint != x5t_s256 {
+            return Err(ERR_CLIENT_AUTHENTICATION_FAILED.with_internal_error(
+                "the refresh token is bound to another client certificate".to_string(),
+            ));
+        }
+
+        let application = repository.get_login_application(&record.application_id)?;
+        check_client_certificate(&application, x5t_s256.as_deref())?;
+        let subject_id = token_subject_id(
+            repository,
+            config,
+            &record.user_id,
+            &record.application_id,
+            &application,
+        )?;
+        // an unbound family stays unbound, only its access tokens are bound to the proof
+        // or the certificate
+        let access_token = issue_access_token(
+            config,
+            &subject_id,
+            &application.audience,
+            record.auth_time,
+            Confirmation {
+                jkt: dpop_jkt.clone(),
+                x5t_s256,
+            },
+        )?;
+        let refresh_token = generate_refresh_token();
+        let rotated = repository.rotate_refresh_token(
+            &token_hash,
+            NewRefreshToken {
+                token_hash: &hash_refresh_token(&refresh_token),
+                family_id: &record.family_id,
+                user_id: &record.user_id,
+                application_id: &record.application_id,
+                expires_at: SystemTime::now() + config.refresh_lifetime,
+                auth_time: record.auth_time,
+                dpop_jkt: record.dpop_jkt.as_deref(),
+                certificate_thumbprint: record.certificate_thumbprint.as_deref(),
+            },
+        )?;
+        if !rotated {
+            // the token was used by a concurrent request
+            return Err(reuse_detected(repository, &record.family_id));
+        }
+        Ok(issued_tokens(
+            config,
+            access_token,
+            refresh_token,
+            dpop_jkt.is_some(),
+        ))
+    })
+    .await
+}
+
+/// revokes an access or refresh token (RFC 7009)
+/// the calling application authenticates like for the introspection and can only
+/// revoke the tokens issued for it, revoking a refresh token revokes its whole family,
+/// unknown or invalid tokens are ignored as the specification requires
+pub async fn revoke<R: Repository>(
+    repository: &R,
+    client: ClientAuthentication<'_>,
+    request: RevocationRequest<'_>,
+) -> Result<(), ErrorDetails> {
+    let application = authenticate_application(repository, &client).await?;
+    let application_id = client_application_id(&client)?;
+    let token = request.token.to_string();
+    let refresh_token_hint = request.token_type_hint == Some("refresh_token");
+    with_repository(repository, move |repository| {
+        if refresh_token_hint {
+            if revoke_refresh_token(repository, &application_id, &token)? {
+                return Ok(());
+            }
+            revoke_access_token(repository, &application, &token)?;
+        } else if !revoke_access_token(repository, &application, &token)? {
+            revoke_refresh_token(repository, &application_id, &token)?;
+        }
+        Ok(())
+    })
+    .await
+}
+
+/// returns false if the token is not a refresh token
+fn revoke_refresh_token<R: TokenRepository>(
+    repository: &R,
+    application_id: &Uuid,
+    refresh_token: &str,
+) -> Result<bool, ErrorDetails> {
+    let record = match repository.get_refresh_token(&hash_refresh_token(refresh_token)) {
+        Ok(record) => record,
+        Err(e) if e.code_name == ERR_INVALID_REFRESH_TOKEN.code_name => return Ok(false),
+        Err(e) => return Err(e),
+    };
+    if record.application_id != *application_id {
+        return Err(ERR_TOKEN_INVALID_AUDIENCE.with_internal_error(format!(
+            "the refresh token was issued for the application {}",
+            record.application_id
+        )));
+    }
+    repository.revoke_refresh_token_family(&record.family_id)?;
+    Ok(true)
+}
+
+/// returns false if the token is not an access token issued by this server
+fn revoke_access_token<R: TokenRepository>(
+    repository: &R,
+    application: &LoginApplication,
+    access_token: &str,
+) -> Result<bool, ErrorDetails> {
+    let config = get_token_issuer_config()?;
+    let (jwt_id, expires_at, audiences) = match config.format.read_token_id(access_token) {
+        Ok(token_id) => token_id,
+        Err(_) => return Ok(false),
+    };
+    if !audiences.contains(&application.audience) {
+        return Err(ERR_TOKEN_INVALID_AUDIENCE.with_internal_error(format!(
+            "the access token was issued for {}",
+            audiences.join(", ")
+        )));
+    }
+    // an expired token can not be used anyway
+    if expires_at > SystemTime::now() {
+        RevocationStore::new(repository).revoke(&jwt_id, expires_at)?;
     }
+    Ok(true)
+}
+
+/// returns the state of an access token (RFC 7662)
+/// the calling application must authenticate with its secret and can only
+/// introspect the tokens issued for its own audience, any invalid, expired,
+/// revoked or foreign token is reported as inactive without more details
+pub async fn introspect<R: Repository>(
+    repository: &R,
+    client: ClientAuthentication<'_>,
+    request: IntrospectionRequest<'_>,
+) -> Result<IntrospectionResponse, ErrorDetails> {
+    let application = authenticate_application(repository, &client).await?;
+    let config = get_token_issuer_config()?;
+    let token = request.token.to_string();
+    // the revocation and the pairwise subjects are checked in the database
+    with_repository(repository, move |repository| {
+        // the binding is returned in `cnf`, the resource server checks it with the request
+        let mut options =
+            validation_options(config, &application.audience).without_confirmation_check();
+        if application.pairwise_subject {
+            // the pairwise subject must still belong to an user
+            let store = PairwiseSubjectStore::new(repository.clone());
+            options = options.with_subject_resolver(Arc::new(store));
+        }
+        let revocation_store = RevocationStore::new(repository);
+        let claims =
+            match config
+                .format
+                .read_token_claims(&token, &options, Some(&revocation_store))
+            {
+                Ok(claims) => claims,
+                Err(_) => return Ok(IntrospectionResponse::default()),
+            };
+        let token_type = match claims.custom.cnf.jkt.is_some() {
+            true => "DPoP",
+            false => "Bearer",
+        };
+        Ok(IntrospectionResponse {
+            active: true,
+            token_type: Some(token_type.to_string()),
+            scope: match claims.custom.scopes.is_empty() {
+                true => None,
+                false => Some(claims.custom.scopes.join(" ")),
+            },
+            sub: Some(claims.subject),
+            aud: Some(claims.audiences),
+            iss: Some(claims.issuer),
+            jti: claims.jwt_id,
+            exp: Some(unix_time(claims.expires_at)),
+            iat: Some(unix_time(claims.issued_at)),
+            nbf: Some(unix_time(claims.not_before)),
+            cnf: token_confirmation(claims.custom.cnf),
+        })
+    })
+    .await
+}
+
+/// exchanges an access token of the calling application (the actor) for a token
+/// of another audience (RFC 8693), so a service can call another one on behalf of the user
+///
+/// the new token is narrower than the subject token: a single audience, at most the same
+/// scopes and lifetime, and the delegation chain is recorded in its `act` claim,
+/// the subject tokens bound to a DPoP key are rejected as the request has no proof,
+/// the ones bound to a client certificate are only accepted from the application
+/// authenticating with that certificate
+pub async fn exchange_token<R: Repository>(
+    repository: &R,
+    client: ClientAuthentication<'_>,
+    request: TokenExchangeRequest<'_>,
+) -> Result<ExchangedToken, ErrorDetails> {
+    if request.grant_type != TOKEN_EXCHANGE_GRANT_TYPE {
+        return Err(ERR_INVALID_DATA
+            .with_internal_error(format!("unsupported grant type {}", request.grant_type)));
+    }
+    if request.subject_token_type != ACCESS_TOKEN_TYPE {
+        return Err(ERR_INVALID_DATA.with_internal_error(format!(
+            "unsupported subject token type {}",
+            request.subject_token_type
+        )));
+    }
+    let actor = authenticate_application(repository, &client).await?;
+    let actor_id = match &client {
+        ClientAuthentication::Secret(credentials) => credentials.client_id,
+        ClientAuthentication::Certificate { client_id, .. } => *client_id,
+    }
+    .to_string();
+    let certificate_thumbprint = match &client {
+        ClientAuthentication::Certificate { certificate, .. } => {
+            Some(certificate_thumbprint(certificate.der))
+        }
+        ClientAuthentication::Secret(_) => None,
+    };
+    let config = get_token_issuer_config()?;
+    let subject_token = request.subject_token.to_string();
+    let audience = request.audience.to_string();
+    let scope = request.scope.map(str::to_string);
+    with_repository(repository, move |repository| {
+        // the subject token must have been issued for the actor,
+        // a token bound to its certificate is accepted on the same connection
+        let mut options = validation_options(config, &actor.audience);
+        if let Some(x5t_s256) = &certificate_thumbprint {
+            options = options.with_certificate_thumbprint(x5t_s256);
+        }
+        if actor.pairwise_subject {
+            let store = PairwiseSubjectStore::new(repository.clone());
+            options = options.with_subject_resolver(Arc::new(store));
+        }
+        let revocation_store = RevocationStore::new(repository);
+        let subject =
+            config
+                .format
+                .read_token_claims(&subject_token, &options, Some(&revocation_store))?;
+
+        let (target_id, target) = match repository.get_login_application_by_audience(&audience) {
+            Ok(target) => target,
+            Err(e) if e.code_name == ERR_DATABASE_RESOURCE_NOT_FOUND.code_name => {
+                return Err(
+                    ERR_INVALID_TARGET.with_internal_error(e.internal_error.unwrap_or_default())
+                )
+            }
+            Err(e) => return Err(e),
+        };
+        let scopes = match scope {
+            Some(scope) => {
+                let scopes: Vec<String> = scope.split_whitespace().map(str::to_string).collect();
+                if let Some(scope) = scopes.iter().find(|s| !subject.custom.scopes.contains(s)) {
+                    return Err(ERR_INVALID_SCOPE
+                        .with_internal_error(format!("the subject token has no {} scope", scope)));
+                }
+                scopes
+            }
+            None => subject.custom.scopes.clone(),
+        };
+        let subject_id = token_subject_id(
+            repository,
+            config,
+            &subject.user_data.user_id,
+            &target_id,
+            &target,
+        )?;
+        let (access_token, lifetime) = issue_exchanged_token(
+            config,
+            &subject_id,
+            &target.audience,
+            &subject,
+            &actor_id,
+            scopes.clone(),
+        )?;
+        Ok(ExchangedToken {
+            access_token,
+            issued_token_type: ACCESS_TOKEN_TYPE.to_string(),
+            token_type: "Bearer".to_string(),
+            expires_in: lifetime.as_secs(),
+            scope: match scopes.is_empty() {
+                true => None,
+                false => Some(scopes.join(" ")),
+            },
+        })
+    })
+    .await
+}
+
+/// authenticates a registered application with its secret or its client certificate,
+/// the applications without a secret or a certificate can not authenticate
+async fn authenticate_application<R: Repository>(
+    repository: &R,
+    client: &ClientAuthentication<'_>,
+) -> Result<LoginApplication, ErrorDetails> {
+    let application_id = client_application_id(client)?;
+    let application = match with_repository(repository, move |repository| {
+        repository.get_login_application(&application_id)
+    })
+    .await
+    {
+        Ok(application) => application,
+        Err(e) if e.code_name == ERR_DATABASE_RESOURCE_NOT_FOUND.code_name => {
+            return Err(ERR_CLIENT_AUTHENTICATION_FAILED
+                .with_internal_error(format!("unknown application {}", application_id)))
+        }
+        Err(e) => return Err(e),
+    };
+    match client {
+        ClientAuthentication::Secret(credentials) => {
+            let secret_hash = application.client_secret_hash.clone().ok_or_else(|| {
+                ERR_CLIENT_AUTHENTICATION_FAILED
+                    .with_internal_error(format!("application {} has no secret", application_id))
+            })?;
+            get_hashing_pool()?
+                .verify_password::<Argon2Hasher>(credentials.client_secret, secret_hash)
+                .await?
+                .map_err(|e| ERR_CLIENT_AUTHENTICATION_FAILED.with_internal_error(e))?;
+        }
+        ClientAuthentication::Certificate { certificate, .. } => {
+            if application.certificate_thumbprint.is_none() {
+                return Err(
+                    ERR_CLIENT_AUTHENTICATION_FAILED.with_internal_error(format!(
+                        "application {} has no client certificate",
+                        application_id
+                    )),
+                );
+            }
+            let x5t_s256 = certificate_thumbprint(certificate.der);
+            check_client_certificate(&application, Some(&x5t_s256))?;
+        }
+    }
+    Ok(application)
+}
+
+/// the id of the application authenticating, its `client_id`
+fn client_application_id(client: &ClientAuthentication<'_>) -> Result<Uuid, ErrorDetails> {
+    let client_id = match client {
+        ClientAuthentication::Secret(credentials) => credentials.client_id,
+        ClientAuthentication::Certificate { client_id, .. } => *client_id,
+    };
+    Uuid::parse_str(client_id)
+        .map_err(|e| ERR_CLIENT_AUTHENTICATION_FAILED.with_internal_error(e.to_string()))
+}
+
+/// an application with a registered client certificate must present it
+fn check_client_certificate(
+    application: &LoginApplication,
+    x5t_s256: Option<&str>,
+) -> Result<(), ErrorDetails> {
+    match &application.certificate_thumbprint {
+        Some(registered) if Some(registered.as_str()) != x5t_s256 => {
+            Err(ERR_CLIENT_AUTHENTICATION_FAILED.with_internal_error(
+                "the client certificate does not match the application".to_string(),
+            ))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// returns the user id written in the tokens of the application,
+/// the pairwise subjects are recorded so they can be mapped back to the user
+fn token_subject_id<R: UserRepository>(
+    repository: &R,
+    config: &TokenIssuerConfig,
+    user_id: &str,
+    application_id: &Uuid,
+    application: &LoginApplication,
+) -> Result<String, ErrorDetails> {
+    if !application.pairwise_subject {
+        return Ok(user_id.to_string());
+    }
+    let subject_id = pairwise_subject_id(config, user_id, &application.audience)?;
+    repository.insert_pairwise_subject(&subject_id, user_id, application_id)?;
+    Ok(subject_id)
+}
+
+fn token_confirmation(cnf: Confirmation) -> Option<TokenConfirmation> {
+    match cnf.jkt.is_none() && cnf.x5t_s256.is_none() {
+        true => None,
+        false => Some(TokenConfirmation {
+            jkt: cnf.jkt,
+            x5t_s256: cnf.x5t_s256,
+        }),
+    }
+}
+
+fn unix_time(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn reuse_detected<R: TokenRepository>(repository: &R, family_id: &Uuid) -> ErrorDetails {
+    match repository.revoke_refresh_token_family(family_id) {
+        Ok(_) => ERR_REFRESH_TOKEN_REUSED
+            .with_internal_error(format!("refresh token family {} revoked", family_id)),
+        Err(e) => e,
+    }
+}
+
+fn issued_tokens(
+    config: &TokenIssuerConfig,
+    access_token: String,
+    refresh_token: String,
+    dpop_bound: bool,
+) -> IssuedTokens {
+    IssuedTokens {
+        access_token,
+        refresh_token,
+        expires_in: config.lifetime.as_secs(),
+        token_type: match dpop_bound {
+            true => "DPoP".to_string(),
+            false => "Bearer".to_string(),
+        },
+    }
+}
+
+/// returns the public signing keys of the tokens as a JWK Set (json)
+pub fn get_jwks() -> Result<String, ErrorDetails> {
+    let config = get_token_issuer_config()?;
+    Ok(config.format.to_jwk_set()?.to_string())
 }
 
-pub fn register_new_user_email_password(
-    credentials: UserCredentials,
+pub async fn register_new_user_email_password<R: Repository>(
+    repository: &R,
+    credentials: UserCredentials<'_>,
 ) -> Result<String, ErrorDetails> {
-    let connection = &mut get_database_connection()?;
     // validate the email
     validate_email(credentials.email)?;
     // hash the password
-    let password_hash =
-        <Argon2Hasher as PasswordHasher>::hash_password(credentials.password.as_bytes())
-            .map_err(|e| ERR_UNKNOWN_INTERNAL_ERROR.with_internal_error(e.to_string()))?;
-    reg_new_user_email_password(connection, credentials.email, &password_hash)
+    let password_hash = get_hashing_pool()?
+        .hash_password::<Argon2Hasher>(credentials.password)
+        .await?;
+    let email = credentials.email.to_string();
+    with_repository(repository, move |repository| {
+        repository.register_user_email_password(&email, &password_hash)
+    })
+    .await
 }
 
 pub fn validate_email(email: &str) -> Result<(), ErrorDetails> {
@@ -1,4 +1,5 @@
 use serde::Serialize;
+use token_helper::error::TokenError;
 
 #[derive(Serialize, Debug)]
 pub struct ErrorDetails {
@@ -16,12 +17,39 @@ impl ErrorDetails {
         ErrorDetails {
             http_code: self.http_code,
             code_name: self.code_name,
-            message: self.message.clone(),
+            message: self.message,
             internal_error: Some(internal_error),
         }
     }
 }
 
+impl From<TokenError> for ErrorDetails {
+    /// maps the token errors to the error list,
+    /// the details of the token error are kept as the internal error
+    fn from(error: TokenError) -> Self {
+        let details = match error {
+            TokenError::InvalidKey(_) => ERR_TOKEN_CONFIGURATION_NOT_FOUND,
+            TokenError::Encoding(_) | TokenError::ReservedClaim(_) => ERR_TOKEN_CREATION_FAILED,
+            TokenError::MalformedHeader(_)
+            | TokenError::MissingClaim(_)
+            | TokenError::InvalidClaim(_) => ERR_TOKEN_MALFORMED,
+            TokenError::UnknownKey(_)
+            | TokenError::Decryption(_)
+            | TokenError::InvalidSignature(_)
+            | TokenError::ForbiddenClaim(_) => ERR_INVALID_TOKEN,
+            TokenError::InvalidIssuer => ERR_TOKEN_INVALID_ISSUER,
+            TokenError::InvalidAudience => ERR_TOKEN_INVALID_AUDIENCE,
+            TokenError::Expired | TokenError::TooOld => ERR_TOKEN_EXPIRED,
+            TokenError::IssuedInFuture | TokenError::NotYetValid => ERR_TOKEN_NOT_YET_VALID,
+            TokenError::Revoked => ERR_TOKEN_REVOKED,
+            TokenError::RevocationCheck(_) => ERR_BACKEND_QUERY_FAILED,
+            TokenError::InvalidSubject(_) => ERR_TOKEN_INVALID_SUBJECT,
+            TokenError::InvalidProof(_) => ERR_INVALID_DPOP_PROOF,
+        };
+        details.with_internal_error(error.to_string())
+    }
+}
+
 /// error list
 // unknown internal error
 pub const ERR_UNKNOWN_INTERNAL_ERROR: ErrorDetails = ErrorDetails {
@@ -87,3 +115,136 @@ pub const ERR_DATABASE_RECORD_EXISTS: ErrorDetails = ErrorDetails {
     message: "The record already exists",
     internal_error: None,
 };
+// token issuer settings not found or unreadable
+pub const ERR_TOKEN_CONFIGURATION_NOT_FOUND: ErrorDetails = ErrorDetails {
+    http_code: 500,
+    code_name: "ERR-TOKEN-CONFIGURATION-NOT-FOUND",
+    message: "The server is not configured to issue tokens",
+    internal_error: None,
+};
+// the access token could not be created
+pub const ERR_TOKEN_CREATION_FAILED: ErrorDetails = ErrorDetails {
+    http_code: 500,
+    code_name: "ERR-TOKEN-CREATION-FAILED",
+    message: "Could not create the access token",
+    internal_error: None,
+};
+// the refresh token is unknown, expired or revoked
+pub const ERR_INVALID_REFRESH_TOKEN: ErrorDetails = ErrorDetails {
+    http_code: 401,
+    code_name: "ERR-INVALID-REFRESH-TOKEN",
+    message: "The refresh token is invalid or expired",
+    internal_error: None,
+};
+// an already rotated refresh token was used again
+pub const ERR_REFRESH_TOKEN_REUSED: ErrorDetails = ErrorDetails {
+    http_code: 401,
+    code_name: "ERR-REFRESH-TOKEN-REUSED",
+    message: "The refresh token was already used, the session has been revoked",
+    internal_error: None,
+};
+// the calling application could not be authenticated
+pub const ERR_CLIENT_AUTHENTICATION_FAILED: ErrorDetails = ErrorDetails {
+    http_code: 401,
+    code_name: "ERR-CLIENT-AUTHENTICATION-FAILED",
+    message: "The application could not be authenticated",
+    internal_error: None,
+};
+// the token is not a well formed jwt or misses a required claim
+pub const ERR_TOKEN_MALFORMED: ErrorDetails = ErrorDetails {
+    http_code: 400,
+    code_name: "ERR-TOKEN-MALFORMED",
+    message: "The token is malformed",
+    internal_error: None,
+};
+// the token could not be decrypted or its signature is invalid
+pub const ERR_INVALID_TOKEN: ErrorDetails = ErrorDetails {
+    http_code: 401,
+    code_name: "ERR-INVALID-TOKEN",
+    message: "The token is invalid",
+    internal_error: None,
+};
+// the token was issued by another issuer
+pub const ERR_TOKEN_INVALID_ISSUER: ErrorDetails = ErrorDetails {
+    http_code: 401,
+    code_name: "ERR-TOKEN-INVALID-ISSUER",
+    message: "The token was not issued by this server",
+    internal_error: None,
+};
+// the token was issued for another application
+pub const ERR_TOKEN_INVALID_AUDIENCE: ErrorDetails = ErrorDetails {
+    http_code: 403,
+    code_name: "ERR-TOKEN-INVALID-AUDIENCE",
+    message: "The token was not issued for this application",
+    internal_error: None,
+};
+// the token is past its expiration time
+pub const ERR_TOKEN_EXPIRED: ErrorDetails = ErrorDetails {
+    http_code: 401,
+    code_name: "ERR-TOKEN-EXPIRED",
+    message: "The token has expired",
+    internal_error: None,
+};
+// the token can not be used yet
+pub const ERR_TOKEN_NOT_YET_VALID: ErrorDetails = ErrorDetails {
+    http_code: 401,
+    code_name: "ERR-TOKEN-NOT-YET-VALID",
+    message: "The token is not valid yet",
+    internal_error: None,
+};
+// the token was revoked before it expired
+pub const ERR_TOKEN_REVOKED: ErrorDetails = ErrorDetails {
+    http_code: 401,
+    code_name: "ERR-TOKEN-REVOKED",
+    message: "The token has been revoked",
+    internal_error: None,
+};
+// the subject of the token is not a valid user
+pub const ERR_TOKEN_INVALID_SUBJECT: ErrorDetails = ErrorDetails {
+    http_code: 401,
+    code_name: "ERR-TOKEN-INVALID-SUBJECT",
+    message: "The token subject is invalid",
+    internal_error: None,
+};
+// the DPoP proof is invalid or does not match the key of the session
+pub const ERR_INVALID_DPOP_PROOF: ErrorDetails = ErrorDetails {
+    http_code: 400,
+    code_name: "ERR-INVALID-DPOP-PROOF",
+    message: "The DPoP proof is invalid",
+    internal_error: None,
+};
+// the audience requested in a token exchange is not a registered application
+pub const ERR_INVALID_TARGET: ErrorDetails = ErrorDetails {
+    http_code: 400,
+    code_name: "ERR-INVALID-TARGET",
+    message: "The requested audience is invalid",
+    internal_error: None,
+};
+// the scope requested in a token exchange is broader than the scope of the subject token
+pub const ERR_INVALID_SCOPE: ErrorDetails = ErrorDetails {
+    http_code: 400,
+    code_name: "ERR-INVALID-SCOPE",
+    message: "The requested scope is invalid",
+    internal_error: None,
+};
+// the password hashing settings are invalid
+pub const ERR_HASHING_CONFIGURATION_INVALID: ErrorDetails = ErrorDetails {
+    http_code: 500,
+    code_name: "ERR-HASHING-CONFIGURATION-INVALID",
+    message: "The server is not configured to hash the passwords",
+    internal_error: None,
+};
+// too many requests are waiting for a password hashing thread
+pub const ERR_HASHING_QUEUE_FULL: ErrorDetails = ErrorDetails {
+    http_code: 503,
+    code_name: "ERR-HASHING-QUEUE-FULL",
+    message: "The server is too busy, try again later",
+    internal_error: None,
+};
+// the migrations of the database could not be read or applied
+pub const ERR_DATABASE_MIGRATION_FAILED: ErrorDetails = ErrorDetails {
+    http_code: 500,
+    code_name: "ERR-DATABASE-MIGRATION-FAILED",
+    message: "The database could not be migrated",
+    internal_error: None,
+};
@@ -1,3 +1,4 @@
 pub mod endpoints;
 pub mod errors;
 pub mod model;
+pub mod repository;
@@ -1,9 +1,147 @@
-use rocket_okapi::okapi::{schemars, schemars::JsonSchema};
-use serde::{Deserialize, Serialize};
-
-#[derive(Serialize, Deserialize, JsonSchema)]
-pub struct UserCredentials<'r> {
-    pub email: &'r str,
-    pub password: &'r str,
-}
-
+use rocket_okapi::okapi::{schemars, schemars::JsonSchema};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct UserCredentials<'r> {
+    pub email: &'r str,
+    pub password: &'r str,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct LoginCredentials<'r> {
+    pub email: &'r str,
+    pub password: &'r str,
+    // the id of the registered application (login_applications) requesting the token
+    pub application_id: &'r str,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct RefreshRequest<'r> {
+    pub refresh_token: &'r str,
+}
+
+/// the DPoP proof (RFC 9449) sent in the `DPoP` header of a token request,
+/// with the method and the full uri of the request it was made for
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct DpopRequest<'r> {
+    pub proof: &'r str,
+    pub method: &'r str,
+    pub uri: &'r str,
+}
+
+/// token revocation request (RFC 7009)
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct RevocationRequest<'r> {
+    pub token: &'r str,
+    // access_token or refresh_token, only used to choose which kind is checked first
+    pub token_type_hint: Option<&'r str>,
+}
+
+/// the tokens issued after a successful login or refresh
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct IssuedTokens {
+    pub access_token: String,
+    pub refresh_token: String,
+    // lifetime of the access token in seconds
+    pub expires_in: u64,
+    // DPoP for the tokens bound to a DPoP key, Bearer otherwise
+    pub token_type: String,
+}
+
+/// token exchange request (RFC 8693), the subject token is an access token
+/// of the calling application exchanged for a token of another audience
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct TokenExchangeRequest<'r> {
+    // urn:ietf:params:oauth:grant-type:token-exchange
+    pub grant_type: &'r str,
+    pub subject_token: &'r str,
+    // urn:ietf:params:oauth:token-type:access_token
+    pub subject_token_type: &'r str,
+    pub audience: &'r str,
+    // space separated list of scopes, at most the scopes of the subject token
+    pub scope: Option<&'r str>,
+}
+
+/// token exchange response (RFC 8693)
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct ExchangedToken {
+    pub access_token: String,
+    pub issued_token_type: String,
+    pub token_type: String,
+    // lifetime of the access token in seconds
+    pub expires_in: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+}
+
+/// the credentials of a registered application (login_applications)
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct ClientCredentials<'r> {
+    pub client_id: &'r str,
+    pub client_secret: &'r str,
+}
+
+/// the client certificate presented on the TLS connection (DER encoded)
+pub struct ClientCertificate<'r> {
+    pub der: &'r [u8],
+}
+
+/// how a registered application authenticates itself
+pub enum ClientAuthentication<'r> {
+    // the secret of the application
+    Secret(ClientCredentials<'r>),
+    // the client certificate registered for the application (mutual TLS, RFC 8705)
+    Certificate {
+        client_id: &'r str,
+        certificate: ClientCertificate<'r>,
+    },
+}
+
+/// token introspection request (RFC 7662)
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct IntrospectionRequest<'r> {
+    pub token: &'r str,
+    // only access tokens can be introspected at the moment
+    pub token_type_hint: Option<&'r str>,
+}
+
+/// token introspection response (RFC 7662)
+/// only `active` is set for an inactive token
+#[derive(Serialize, Deserialize, JsonSchema, Default)]
+pub struct IntrospectionResponse {
+    pub active: bool,
+    // space separated list of the granted scopes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aud: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>,
+    // seconds since the unix epoch
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exp: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iat: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nbf: Option<u64>,
+    // the key the token is bound to (RFC 7800)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cnf: Option<TokenConfirmation>,
+}
+
+/// the confirmation claim of a token bound to a DPoP key or a client certificate
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct TokenConfirmation {
+    // SHA-256 thumbprint of the DPoP key
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jkt: Option<String>,
+    // SHA-256 thumbprint of the client certificate
+    #[serde(rename = "x5t#S256", skip_serializing_if = "Option::is_none")]
+    pub x5t_s256: Option<String>,
+}
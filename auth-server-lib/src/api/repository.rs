@@ -0,0 +1,203 @@
+use std::time::SystemTime;
+
+use uuid::Uuid;
+
+use crate::api::errors::ErrorDetails;
+
+#[cfg(feature = "sqlite")]
+pub use crate::util::database::sqlite::{SqlitePool, SqliteRepository};
+pub use crate::util::database::{
+    connection::DatabasePool,
+    login_application::LoginApplication,
+    memory::InMemoryRepository,
+    migrations::MigrationStatus,
+    postgres::PgRepository,
+    refresh_token::{NewRefreshToken, RefreshTokenRecord},
+    user_email::UserCredentialsRecord,
+};
+
+/// the users, their credentials and their pairwise subjects
+pub trait UserRepository {
+    /// returns the user id and the password hash of the user with the email
+    fn get_user_credentials(&self, email: &str) -> Result<UserCredentialsRecord, ErrorDetails>;
+
+    /// creates an user with an email and a password, returns the new user id
+    fn register_user_email_password(
+        &self,
+        email: &str,
+        password_hash: &str,
+    ) -> Result<String, ErrorDetails>;
+
+    /// records the pairwise subject of an user for the application,
+    /// the subject is stable so it is only stored the first time
+    fn insert_pairwise_subject(
+        &self,
+        subject: &str,
+        user_id: &str,
+        application_id: &Uuid,
+    ) -> Result<(), ErrorDetails>;
+
+    /// returns the user id behind the pairwise subject of an application with the audience
+    fn get_pairwise_subject_user_id(
+        &self,
+        subject: &str,
+        audience: &str,
+    ) -> Result<Option<String>, ErrorDetails>;
+}
+
+/// the applications the users log in to
+pub trait ApplicationRepository {
+    fn get_login_application(
+        &self,
+        application_id: &Uuid,
+    ) -> Result<LoginApplication, ErrorDetails>;
+
+    /// returns the registered application of an audience,
+    /// an audience shared by several applications is rejected as ambiguous
+    fn get_login_application_by_audience(
+        &self,
+        audience: &str,
+    ) -> Result<(Uuid, LoginApplication), ErrorDetails>;
+}
+
+/// the refresh tokens and the revoked access tokens
+pub trait TokenRepository {
+    /// returns the refresh token with the hash, an unknown token is `ERR_INVALID_REFRESH_TOKEN`
+    fn get_refresh_token(&self, token_hash: &str) -> Result<RefreshTokenRecord, ErrorDetails>;
+
+    fn insert_refresh_token(&self, new_token: NewRefreshToken) -> Result<(), ErrorDetails>;
+
+    /// marks the old token as used and stores its replacement in the same family
+    /// returns false if the old token was already used (by a concurrent refresh)
+    fn rotate_refresh_token(
+        &self,
+        old_token_hash: &str,
+        new_token: NewRefreshToken,
+    ) -> Result<bool, ErrorDetails>;
+
+    /// revokes every refresh token of the family
+    fn revoke_refresh_token_family(&self, family_id: &Uuid) -> Result<(), ErrorDetails>;
+
+    /// stores the id of a revoked token until it expires
+    fn insert_revoked_token(
+        &self,
+        jwt_id: &str,
+        expires_at: SystemTime,
+    ) -> Result<(), ErrorDetails>;
+
+    /// returns the expiration time of the token if it was revoked
+    fn get_revoked_token(&self, jwt_id: &str) -> Result<Option<SystemTime>, ErrorDetails>;
+}
+
+/// everything stored by the endpoints, cloned when a request needs its own handle
+/// (the repositories share their storage between the clones)
+pub trait Repository:
+    UserRepository + ApplicationRepository + TokenRepository + Clone + Send + Sync + 'static
+{
+}
+
+impl<T> Repository for T where
+    T: UserRepository + ApplicationRepository + TokenRepository + Clone + Send + Sync + 'static
+{
+}
+
+/// the repository of the database selected by the scheme of `DATABASE_URL`
+/// (see `endpoints::database_repository`)
+#[derive(Clone)]
+pub enum DatabaseRepository {
+    Postgres(PgRepository),
+    #[cfg(feature = "sqlite")]
+    Sqlite(SqliteRepository),
+}
+
+// calls the same method on the repository of every database
+macro_rules! dispatch {
+    ($self:ident, $repository:ident => $call:expr) => {
+        match $self {
+            DatabaseRepository::Postgres($repository) => $call,
+            #[cfg(feature = "sqlite")]
+            DatabaseRepository::Sqlite($repository) => $call,
+        }
+    };
+}
+
+impl UserRepository for DatabaseRepository {
+    fn get_user_credentials(&self, email: &str) -> Result<UserCredentialsRecord, ErrorDetails> {
+        dispatch!(self, repository => repository.get_user_credentials(email))
+    }
+
+    fn register_user_email_password(
+        &self,
+        email: &str,
+        password_hash: &str,
+    ) -> Result<String, ErrorDetails> {
+        dispatch!(self, repository => repository.register_user_email_password(email, password_hash))
+    }
+
+    fn insert_pairwise_subject(
+        &self,
+        subject: &str,
+        user_id: &str,
+        application_id: &Uuid,
+    ) -> Result<(), ErrorDetails> {
+        dispatch!(self, repository => repository.insert_pairwise_subject(subject, user_id, application_id))
+    }
+
+    fn get_pairwise_subject_user_id(
+        &self,
+        subject: &str,
+        audience: &str,
+    ) -> Result<Option<String>, ErrorDetails> {
+        dispatch!(self, repository => repository.get_pairwise_subject_user_id(subject, audience))
+    }
+}
+
+impl ApplicationRepository for DatabaseRepository {
+    fn get_login_application(
+        &self,
+        application_id: &Uuid,
+    ) -> Result<LoginApplication, ErrorDetails> {
+        dispatch!(self, repository => repository.get_login_application(application_id))
+    }
+
+    fn get_login_application_by_audience(
+        &self,
+        audience: &str,
+    ) -> Result<(Uuid, LoginApplication), ErrorDetails> {
+        dispatch!(self, repository => repository.get_login_application_by_audience(audience))
+    }
+}
+
+impl TokenRepository for DatabaseRepository {
+    fn get_refresh_token(&self, token_hash: &str) -> Result<RefreshTokenRecord, ErrorDetails> {
+        dispatch!(self, repository => repository.get_refresh_token(token_hash))
+    }
+
+    fn insert_refresh_token(&self, new_token: NewRefreshToken) -> Result<(), ErrorDetails> {
+        dispatch!(self, repository => repository.insert_refresh_token(new_token))
+    }
+
+    fn rotate_refresh_token(
+        &self,
+        old_token_hash: &str,
+        new_token: NewRefreshToken,
+    ) -> Result<bool, ErrorDetails> {
+        dispatch!(self, repository => repository.rotate_refresh_token(old_token_hash, new_token))
+    }
+
+    fn revoke_refresh_token_family(&self, family_id: &Uuid) -> Result<(), ErrorDetails> {
+        dispatch!(self, repository => repository.revoke_refresh_token_family(family_id))
+    }
+
+    fn insert_revoked_token(
+        &self,
+        jwt_id: &str,
+        expires_at: SystemTime,
+    ) -> Result<(), ErrorDetails> {
+        dispatch!(self, repository => repository.insert_revoked_token(jwt_id, expires_at))
+    }
+
+    fn get_revoked_token(&self, jwt_id: &str) -> Result<Option<SystemTime>, ErrorDetails> {
+        dispatch!(self, repository => repository.get_revoked_token(jwt_id))
+    }
+}
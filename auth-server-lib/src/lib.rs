@@ -1,5 +1,7 @@
 pub mod api;
 mod schema;
+#[cfg(feature = "sqlite")]
+mod schema_sqlite;
 mod util;
 
 #[cfg(test)]
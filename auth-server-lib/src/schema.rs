@@ -6,13 +6,49 @@ diesel::table! {
         friendly_name -> Varchar,
         audience -> Varchar,
         callback_url -> Varchar,
+        client_secret_hash -> Nullable<Varchar>,
+        pairwise_subject -> Bool,
+        certificate_thumbprint -> Nullable<Varchar>,
+    }
+}
+
+diesel::table! {
+    pairwise_subjects (subject) {
+        subject -> Varchar,
+        user_id -> Varchar,
+        application_id -> Uuid,
+    }
+}
+
+diesel::table! {
+    refresh_tokens (token_hash) {
+        token_hash -> Varchar,
+        family_id -> Uuid,
+        user_id -> Varchar,
+        application_id -> Uuid,
+        issued_at -> Timestamp,
+        expires_at -> Timestamp,
+        used -> Bool,
+        revoked -> Bool,
+        auth_time -> Timestamp,
+        dpop_jkt -> Nullable<Varchar>,
+        certificate_thumbprint -> Nullable<Varchar>,
+    }
+}
+
+diesel::table! {
+    revoked_tokens (jwt_id) {
+        jwt_id -> Varchar,
+        expires_at -> Timestamp,
+        revoked_at -> Timestamp,
     }
 }
 
 diesel::table! {
     user_emails (user_id) {
         user_id -> Varchar,
-        email -> Varchar,
+        email -> Citext,
+        email_normalized -> Varchar,
     }
 }
 
@@ -29,11 +65,18 @@ diesel::table! {
     }
 }
 
+diesel::joinable!(pairwise_subjects -> login_applications (application_id));
+diesel::joinable!(pairwise_subjects -> users (user_id));
+diesel::joinable!(refresh_tokens -> login_applications (application_id));
+diesel::joinable!(refresh_tokens -> users (user_id));
 diesel::joinable!(user_emails -> users (user_id));
 diesel::joinable!(user_passwords -> users (user_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
     login_applications,
+    pairwise_subjects,
+    refresh_tokens,
+    revoked_tokens,
     user_emails,
     user_passwords,
     users,
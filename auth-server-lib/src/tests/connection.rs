@@ -0,0 +1,23 @@
+use std::time::Duration;
+
+use dboilerplate::util::pool::{create_pool, PoolConfig};
+
+use crate::{
+    api::{errors::*, repository::DatabasePool},
+    util::database::connection::get_database_connection,
+};
+
+#[test]
+fn test_pool_exhausted() {
+    let pool_config = PoolConfig {
+        min_idle: 0,
+        connection_timeout: Duration::from_secs(1),
+        ..PoolConfig::default()
+    };
+    // no connection can be opened, so the pool never has a free one
+    let pool: DatabasePool = create_pool("postgres://localhost:9/offline", &pool_config);
+    let error = get_database_connection(&pool).err().unwrap();
+    assert_eq!(error.code_name, ERR_BACKEND_CONNECTION_FAILED.code_name);
+    assert_eq!(error.http_code, 503);
+    assert!(error.internal_error.is_some());
+}
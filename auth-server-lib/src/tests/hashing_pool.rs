@@ -0,0 +1,102 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    api::errors::ERR_HASHING_QUEUE_FULL,
+    util::security::password_hasher::{argon2::Argon2Hasher, pool::HashingPool},
+};
+
+#[tokio::test]
+async fn test_hashing_pool_round_trip() {
+    let pool = HashingPool::new(1, 16).unwrap();
+    let encoded = pool
+        .hash_password::<Argon2Hasher>("correct horse battery staple")
+        .await
+        .unwrap();
+    let verified = pool
+        .verify_password::<Argon2Hasher>("correct horse battery staple", encoded.clone())
+        .await
+        .unwrap();
+    assert!(verified.is_ok());
+    let verified = pool
+        .verify_password::<Argon2Hasher>("wrong password", encoded)
+        .await
+        .unwrap();
+    assert!(verified.is_err());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_hashing_pool_concurrency_limit() {
+    let pool = Arc::new(HashingPool::new(2, 16).unwrap());
+    let running = Arc::new(AtomicUsize::new(0));
+    let max_running = Arc::new(AtomicUsize::new(0));
+
+    let jobs = (0..8).map(|_| {
+        let pool = pool.clone();
+        let running = running.clone();
+        let max_running = max_running.clone();
+        tokio::spawn(async move {
+            pool.run(move || {
+                let count = running.fetch_add(1, Ordering::SeqCst) + 1;
+                max_running.fetch_max(count, Ordering::SeqCst);
+                thread::sleep(Duration::from_millis(50));
+                running.fetch_sub(1, Ordering::SeqCst);
+            })
+            .await
+            .unwrap();
+        })
+    });
+    for job in jobs.collect::<Vec<_>>() {
+        job.await.unwrap();
+    }
+    assert_eq!(max_running.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn test_hashing_pool_panic() {
+    let pool = HashingPool::new(1, 16).unwrap();
+    // the panicking job fails its request only, the thread keeps running
+    assert!(pool.run(|| panic!("hashing failed")).await.is_err());
+    assert_eq!(pool.run(|| 1).await.unwrap(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_hashing_pool_queue_full() {
+    let pool = Arc::new(HashingPool::new(1, 1).unwrap());
+    // the thread is busy with the first job until it is released
+    let (started_sender, started_receiver) = mpsc::channel();
+    let (release_sender, release_receiver) = mpsc::channel::<()>();
+    let busy = tokio::spawn({
+        let pool = pool.clone();
+        async move {
+            pool.run(move || {
+                started_sender.send(()).unwrap();
+                release_receiver.recv().unwrap();
+            })
+            .await
+        }
+    });
+    tokio::task::spawn_blocking(move || started_receiver.recv().unwrap())
+        .await
+        .unwrap();
+    // the second job waits in the queue
+    let queued = tokio::spawn({
+        let pool = pool.clone();
+        async move { pool.run(|| 2).await }
+    });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let error = pool.run(|| 3).await.unwrap_err();
+    assert_eq!(error.code_name, ERR_HASHING_QUEUE_FULL.code_name);
+
+    release_sender.send(()).unwrap();
+    busy.await.unwrap().unwrap();
+    assert_eq!(queued.await.unwrap().unwrap(), 2);
+    assert_eq!(pool.run(|| 4).await.unwrap(), 4);
+}
@@ -0,0 +1,26 @@
+use serde_json::json;
+
+use crate::api::model::IntrospectionResponse;
+
+#[test]
+fn test_inactive_response() {
+    // an inactive token does not disclose anything else
+    let response = serde_json::to_value(IntrospectionResponse::default()).unwrap();
+    assert_eq!(response, json!({ "active": false }));
+}
+
+#[test]
+fn test_active_response() {
+    let response = IntrospectionResponse {
+        active: true,
+        sub: Some("1234567890abcdef1234567890abcdef12345678:local".to_string()),
+        aud: Some(vec!["test.app".to_string()]),
+        exp: Some(1665000000),
+        ..Default::default()
+    };
+    let response = serde_json::to_value(response).unwrap();
+    assert_eq!(response["active"], json!(true));
+    assert_eq!(response["aud"], json!(["test.app"]));
+    assert_eq!(response["exp"], json!(1665000000));
+    assert!(response.get("jti").is_none());
+}
@@ -1 +1,10 @@
-//pub mod test;
\ No newline at end of file
+mod connection;
+mod hashing_pool;
+mod introspection;
+mod refresh_token;
+mod repository;
+mod revocation;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+mod token_errors;
+mod token_issuer;
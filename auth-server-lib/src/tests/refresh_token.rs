@@ -0,0 +1,20 @@
+use crate::util::security::refresh_token::{generate_refresh_token, hash_refresh_token};
+
+#[test]
+fn test_generate_refresh_token() {
+    let token = generate_refresh_token();
+    assert_eq!(token.len(), 64);
+    assert!(token.chars().all(|c| c.is_ascii_alphanumeric()));
+    assert_ne!(token, generate_refresh_token());
+}
+
+#[test]
+fn test_hash_refresh_token() {
+    let hash = hash_refresh_token("test");
+    // sha-256 of "test"
+    assert_eq!(
+        hash,
+        "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08"
+    );
+    assert_ne!(hash, hash_refresh_token("test2"));
+}
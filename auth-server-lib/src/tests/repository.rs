@@ -0,0 +1,355 @@
+use josekit::jwk::JwkSet;
+use token_helper::{
+    manager::{decode_token, DecodingKey},
+    validation::ValidationOptions,
+};
+use uuid::Uuid;
+
+use crate::{
+    api::{
+        endpoints::{
+            get_jwks, introspect, login, refresh, register_new_user_email_password, revoke,
+        },
+        errors::*,
+        model::{
+            ClientAuthentication, ClientCredentials, IntrospectionRequest, IssuedTokens,
+            LoginCredentials, RefreshRequest, RevocationRequest, UserCredentials,
+        },
+        repository::{InMemoryRepository, LoginApplication, Repository},
+    },
+    util::security::{
+        password_hasher::{argon2::Argon2Hasher, PasswordHasher},
+        token_issuer::{init_token_issuer_config, pairwise_subject_id},
+    },
+};
+
+use super::token_issuer::test_config;
+
+const TEST_EMAIL: &str = "user@example.com";
+const TEST_PASSWORD: &str = "correct horse battery staple";
+const TEST_CLIENT_SECRET: &str = "client secret";
+
+// an application with a secret, its audience is `<id>.app`
+pub(super) fn test_application(pairwise_subject: bool) -> (Uuid, LoginApplication) {
+    init_token_issuer_config(test_config);
+    let application_id = Uuid::new_v4();
+    let client_secret_hash =
+        <Argon2Hasher as PasswordHasher>::hash_password(TEST_CLIENT_SECRET.as_bytes()).unwrap();
+    let application = LoginApplication {
+        audience: format!("{}.app", application_id),
+        client_secret_hash: Some(client_secret_hash),
+        pairwise_subject,
+        certificate_thumbprint: None,
+    };
+    (application_id, application)
+}
+
+fn test_repository(pairwise_subject: bool) -> (InMemoryRepository, Uuid) {
+    let repository = InMemoryRepository::new();
+    let (application_id, application) = test_application(pairwise_subject);
+    repository.insert_login_application(application_id, application);
+    (repository, application_id)
+}
+
+async fn test_register<R: Repository>(repository: &R) -> Result<String, ErrorDetails> {
+    register_new_user_email_password(
+        repository,
+        UserCredentials {
+            email: TEST_EMAIL,
+            password: TEST_PASSWORD,
+        },
+    )
+    .await
+}
+
+async fn test_login<R: Repository>(
+    repository: &R,
+    application_id: &Uuid,
+    password: &str,
+) -> Result<IssuedTokens, ErrorDetails> {
+    login(
+        repository,
+        LoginCredentials {
+            email: TEST_EMAIL,
+            password,
+            application_id: &application_id.to_string(),
+        },
+        None,
+        None,
+    )
+    .await
+}
+
+async fn test_refresh<R: Repository>(
+    repository: &R,
+    refresh_token: &str,
+) -> Result<IssuedTokens, ErrorDetails> {
+    refresh(repository, RefreshRequest { refresh_token }, None, None).await
+}
+
+async fn test_introspect<R: Repository>(
+    repository: &R,
+    application_id: &Uuid,
+    token: &str,
+) -> bool {
+    let client_id = application_id.to_string();
+    let client = ClientAuthentication::Secret(ClientCredentials {
+        client_id: &client_id,
+        client_secret: TEST_CLIENT_SECRET,
+    });
+    let request = IntrospectionRequest {
+        token,
+        token_type_hint: None,
+    };
+    introspect(repository, client, request)
+        .await
+        .unwrap()
+        .active
+}
+
+/// registers an user and logs in to the application
+pub(super) async fn check_register_and_login<R: Repository>(repository: &R, application_id: &Uuid) {
+    test_register(repository).await.unwrap();
+    let tokens = test_login(repository, application_id, TEST_PASSWORD)
+        .await
+        .unwrap();
+    assert_eq!(tokens.token_type, "Bearer");
+    assert!(test_introspect(repository, application_id, &tokens.access_token).await);
+    // the relying services verify the token with the published public keys only
+    let jwk_set = JwkSet::from_bytes(get_jwks().unwrap().as_bytes()).unwrap();
+    let audience = format!("{}.app", application_id);
+    let options = ValidationOptions::new("test-issuer", &[&audience]);
+    let claims = decode_token(
+        &DecodingKey::JwkSet(&jwk_set),
+        &tokens.access_token,
+        &options,
+        None,
+    )
+    .unwrap();
+    assert_eq!(claims.user_data.realm, "test-realm");
+    assert_eq!(claims.custom.amr, vec!["pwd".to_string()]);
+
+    let error = test_login(repository, application_id, "wrong password")
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(error.code_name, ERR_AUTHENTICATION_FAILED.code_name);
+    let error = test_register(repository).await.err().unwrap();
+    assert_eq!(error.code_name, ERR_DATABASE_RECORD_EXISTS.code_name);
+}
+
+/// the rotated refresh tokens can not be used again
+pub(super) async fn check_refresh_token_reuse<R: Repository>(
+    repository: &R,
+    application_id: &Uuid,
+) {
+    test_register(repository).await.unwrap();
+    let tokens = test_login(repository, application_id, TEST_PASSWORD)
+        .await
+        .unwrap();
+    let refreshed = test_refresh(repository, &tokens.refresh_token)
+        .await
+        .unwrap();
+
+    // reusing the rotated token revokes its family
+    let error = test_refresh(repository, &tokens.refresh_token)
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(error.code_name, ERR_REFRESH_TOKEN_REUSED.code_name);
+    let error = test_refresh(repository, &refreshed.refresh_token)
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(error.code_name, ERR_INVALID_REFRESH_TOKEN.code_name);
+}
+
+async fn test_revoke<R: Repository>(
+    repository: &R,
+    application_id: &Uuid,
+    token: &str,
+) -> Result<(), ErrorDetails> {
+    let client_id = application_id.to_string();
+    let client = ClientAuthentication::Secret(ClientCredentials {
+        client_id: &client_id,
+        client_secret: TEST_CLIENT_SECRET,
+    });
+    let request = RevocationRequest {
+        token,
+        token_type_hint: None,
+    };
+    revoke(repository, client, request).await
+}
+
+/// a revoked access token is inactive, the application must authenticate
+pub(super) async fn check_revoke_access_token<R: Repository>(
+    repository: &R,
+    application_id: &Uuid,
+) {
+    test_register(repository).await.unwrap();
+    let tokens = test_login(repository, application_id, TEST_PASSWORD)
+        .await
+        .unwrap();
+
+    let client_id = application_id.to_string();
+    let client = ClientAuthentication::Secret(ClientCredentials {
+        client_id: &client_id,
+        client_secret: "wrong secret",
+    });
+    let request = RevocationRequest {
+        token: &tokens.access_token,
+        token_type_hint: None,
+    };
+    let error = revoke(repository, client, request).await.err().unwrap();
+    assert_eq!(error.code_name, ERR_CLIENT_AUTHENTICATION_FAILED.code_name);
+
+    test_revoke(repository, application_id, &tokens.access_token)
+        .await
+        .unwrap();
+    assert!(!test_introspect(repository, application_id, &tokens.access_token).await);
+    // the refresh tokens are not revoked with the access token
+    test_refresh(repository, &tokens.refresh_token)
+        .await
+        .unwrap();
+}
+
+/// the pairwise subject is recorded for the application only
+/// (the application must have pairwise subjects)
+pub(super) async fn check_pairwise_subject<R: Repository>(repository: &R, application_id: &Uuid) {
+    let user_id = test_register(repository).await.unwrap();
+    let tokens = test_login(repository, application_id, TEST_PASSWORD)
+        .await
+        .unwrap();
+    assert!(test_introspect(repository, application_id, &tokens.access_token).await);
+
+    let audience = format!("{}.app", application_id);
+    let subject_id = pairwise_subject_id(&test_config(), &user_id, &audience).unwrap();
+    let resolved = repository.get_pairwise_subject_user_id(&subject_id, &audience);
+    assert_eq!(resolved.unwrap(), Some(user_id));
+    let resolved = repository.get_pairwise_subject_user_id(&subject_id, "other.app");
+    assert_eq!(resolved.unwrap(), None);
+}
+
+/// the emails are unique without their case and the users log in with any case
+pub(super) async fn check_email_case_insensitive<R: Repository>(
+    repository: &R,
+    application_id: &Uuid,
+) {
+    let user_id = test_register(repository).await.unwrap();
+    let tokens = login(
+        repository,
+        LoginCredentials {
+            email: " USER@Example.com",
+            password: TEST_PASSWORD,
+            application_id: &application_id.to_string(),
+        },
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+    assert!(test_introspect(repository, application_id, &tokens.access_token).await);
+    assert_eq!(
+        repository
+            .get_user_credentials("User@EXAMPLE.com")
+            .unwrap()
+            .user_id,
+        user_id
+    );
+
+    let credentials = UserCredentials {
+        email: "User@Example.COM",
+        password: TEST_PASSWORD,
+    };
+    let error = register_new_user_email_password(repository, credentials)
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(error.code_name, ERR_DATABASE_RECORD_EXISTS.code_name);
+}
+
+/// only one of the concurrent registrations of an email succeeds,
+/// the others fail as an email already in use
+pub(super) async fn check_concurrent_registration<R: Repository>(repository: &R) {
+    let registrations = ["user@example.com", "USER@example.com", "User@Example.com"]
+        .into_iter()
+        .map(|email| {
+            let repository = repository.clone();
+            tokio::spawn(async move {
+                let credentials = UserCredentials {
+                    email,
+                    password: TEST_PASSWORD,
+                };
+                register_new_user_email_password(&repository, credentials).await
+            })
+        })
+        .collect::<Vec<_>>();
+    let mut registered = 0;
+    for registration in registrations {
+        match registration.await.unwrap() {
+            Ok(_) => registered += 1,
+            Err(error) => assert_eq!(error.code_name, ERR_DATABASE_RECORD_EXISTS.code_name),
+        }
+    }
+    assert_eq!(registered, 1);
+}
+
+#[tokio::test]
+async fn test_register_and_login() {
+    let (repository, application_id) = test_repository(false);
+    check_register_and_login(&repository, &application_id).await;
+}
+
+#[tokio::test]
+async fn test_refresh_token_reuse() {
+    let (repository, application_id) = test_repository(false);
+    check_refresh_token_reuse(&repository, &application_id).await;
+}
+
+#[tokio::test]
+async fn test_revoke_access_token() {
+    let (repository, application_id) = test_repository(false);
+    check_revoke_access_token(&repository, &application_id).await;
+}
+
+#[tokio::test]
+async fn test_revoke_foreign_tokens() {
+    let (repository, application_id) = test_repository(false);
+    test_register(&repository).await.unwrap();
+    let tokens = test_login(&repository, &application_id, TEST_PASSWORD)
+        .await
+        .unwrap();
+
+    // the tokens can only be revoked by the application they were issued for
+    let (other_id, other) = test_application(false);
+    repository.insert_login_application(other_id, other);
+    for token in [&tokens.access_token, &tokens.refresh_token] {
+        let error = test_revoke(&repository, &other_id, token)
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(error.code_name, ERR_TOKEN_INVALID_AUDIENCE.code_name);
+    }
+    assert!(test_introspect(&repository, &application_id, &tokens.access_token).await);
+    test_refresh(&repository, &tokens.refresh_token)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_pairwise_subject() {
+    let (repository, application_id) = test_repository(true);
+    check_pairwise_subject(&repository, &application_id).await;
+}
+
+#[tokio::test]
+async fn test_email_case_insensitive() {
+    let (repository, application_id) = test_repository(false);
+    check_email_case_insensitive(&repository, &application_id).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_concurrent_registration() {
+    let (repository, _) = test_repository(false);
+    check_concurrent_registration(&repository).await;
+}
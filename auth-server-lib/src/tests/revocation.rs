@@ -0,0 +1,19 @@
+use std::time::{Duration, SystemTime};
+
+use crate::util::security::revocation::{cache_revoked_token, is_cached_revoked_token};
+
+#[test]
+fn test_cache_revoked_token() {
+    let expires_at = SystemTime::now() + Duration::from_secs(60);
+    assert!(!is_cached_revoked_token("test-cache-revoked"));
+    cache_revoked_token("test-cache-revoked", expires_at);
+    assert!(is_cached_revoked_token("test-cache-revoked"));
+}
+
+#[test]
+fn test_cache_expired_token() {
+    // an expired token does not need to be remembered
+    let expires_at = SystemTime::now() - Duration::from_secs(60);
+    cache_revoked_token("test-cache-expired", expires_at);
+    assert!(!is_cached_revoked_token("test-cache-expired"));
+}
@@ -0,0 +1,217 @@
+use std::path::PathBuf;
+
+use dboilerplate::util::pool::PoolConfig;
+use diesel::prelude::*;
+use diesel_migrations::MigrationHarness;
+use uuid::Uuid;
+
+use crate::{
+    api::{
+        errors::*,
+        repository::{DatabaseRepository, SqliteRepository, UserRepository},
+    },
+    schema_sqlite::login_applications,
+    util::database::{
+        connection::get_database_connection,
+        migrations::{migration_status, run_pending_migrations, SQLITE_MIGRATIONS},
+        sqlite::create_sqlite_pool,
+    },
+};
+
+use super::repository::{
+    check_concurrent_registration, check_email_case_insensitive, check_pairwise_subject,
+    check_refresh_token_reuse, check_register_and_login, check_revoke_access_token,
+    test_application,
+};
+
+// a new database file, removed when dropped
+struct TestDatabase {
+    path: PathBuf,
+    repository: SqliteRepository,
+    application_id: Uuid,
+}
+
+impl TestDatabase {
+    // the database without the tables
+    fn empty() -> Self {
+        let path = std::env::temp_dir().join(format!("auth-server-{}.sqlite", Uuid::new_v4()));
+        let pool = create_sqlite_pool(path.to_str().unwrap(), &PoolConfig::default()).unwrap();
+        Self {
+            path,
+            repository: SqliteRepository::new(pool),
+            application_id: Uuid::nil(),
+        }
+    }
+
+    // the migrated database with an application
+    fn new(pairwise_subject: bool) -> Self {
+        let mut database = Self::empty();
+        run_pending_migrations(&DatabaseRepository::Sqlite(database.repository.clone())).unwrap();
+        let (application_id, application) = test_application(pairwise_subject);
+        let connection = &mut get_database_connection(database.repository.pool()).unwrap();
+        diesel::insert_into(login_applications::table)
+            .values((
+                login_applications::id.eq(application_id.to_string()),
+                login_applications::friendly_name.eq("test"),
+                login_applications::audience.eq(&application.audience),
+                login_applications::callback_url.eq("http://localhost/callback"),
+                login_applications::client_secret_hash.eq(&application.client_secret_hash),
+                login_applications::pairwise_subject.eq(application.pairwise_subject),
+            ))
+            .execute(connection)
+            .unwrap();
+        database.application_id = application_id;
+        database
+    }
+}
+
+impl Drop for TestDatabase {
+    fn drop(&mut self) {
+        for suffix in ["", "-wal", "-shm"] {
+            let mut path = self.path.clone().into_os_string();
+            path.push(suffix);
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_sqlite_register_and_login() {
+    let database = TestDatabase::new(false);
+    check_register_and_login(&database.repository, &database.application_id).await;
+}
+
+#[tokio::test]
+async fn test_sqlite_refresh_token_reuse() {
+    let database = TestDatabase::new(false);
+    check_refresh_token_reuse(&database.repository, &database.application_id).await;
+}
+
+#[tokio::test]
+async fn test_sqlite_revoke_access_token() {
+    let database = TestDatabase::new(false);
+    check_revoke_access_token(&database.repository, &database.application_id).await;
+}
+
+#[tokio::test]
+async fn test_sqlite_pairwise_subject() {
+    let database = TestDatabase::new(true);
+    check_pairwise_subject(&database.repository, &database.application_id).await;
+}
+
+#[tokio::test]
+async fn test_sqlite_email_case_insensitive() {
+    let database = TestDatabase::new(false);
+    check_email_case_insensitive(&database.repository, &database.application_id).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_sqlite_concurrent_registration() {
+    let database = TestDatabase::new(false);
+    check_concurrent_registration(&database.repository).await;
+}
+
+#[test]
+fn test_sqlite_user_id_collision() {
+    let database = TestDatabase::new(false);
+    let repository = &database.repository;
+    let taken = repository
+        .register_user("taken@example.com", "hash", || "taken".to_string())
+        .unwrap();
+    assert_eq!(taken, "taken");
+
+    // the registration is tried again with another id
+    let mut user_ids = vec!["other", "taken"];
+    let user_id = repository
+        .register_user("other@example.com", "hash", || {
+            user_ids.pop().unwrap().to_string()
+        })
+        .unwrap();
+    assert_eq!(user_id, "other");
+
+    let error = repository
+        .register_user("last@example.com", "hash", || "taken".to_string())
+        .err()
+        .unwrap();
+    assert_eq!(error.code_name, ERR_DATABASE_TRANSACTION_FAILED.code_name);
+    assert!(repository.get_user_credentials("last@example.com").is_err());
+}
+
+#[test]
+fn test_sqlite_migrations() {
+    let database = TestDatabase::empty();
+    let repository = DatabaseRepository::Sqlite(database.repository.clone());
+    let status = migration_status(&repository).unwrap();
+    assert!(!status.is_empty());
+    assert!(status.iter().all(|migration| !migration.applied));
+
+    let applied = run_pending_migrations(&repository).unwrap();
+    let names = status.into_iter().map(|migration| migration.name);
+    assert_eq!(applied, names.collect::<Vec<_>>());
+    assert!(migration_status(&repository)
+        .unwrap()
+        .iter()
+        .all(|migration| migration.applied));
+    // the applied migrations are not run again
+    assert!(run_pending_migrations(&repository).unwrap().is_empty());
+}
+
+/// the existing emails are normalized by the migration like by the server,
+/// only their ASCII letters are lowercased
+#[test]
+fn test_sqlite_existing_emails_migration() {
+    let database = TestDatabase::empty();
+    let connection = &mut get_database_connection(database.repository.pool()).unwrap();
+    // the initial schema, before the case insensitive emails
+    connection.run_next_migration(SQLITE_MIGRATIONS).unwrap();
+    for statement in [
+        "insert into users (user_id) values ('existing')",
+        "insert into user_passwords (user_id, password_hash) values ('existing', 'hash')",
+        "insert into user_emails (user_id, email) values ('existing', ' ÉLODIE@Example.com')",
+    ] {
+        diesel::sql_query(statement).execute(connection).unwrap();
+    }
+    run_pending_migrations(&DatabaseRepository::Sqlite(database.repository.clone())).unwrap();
+
+    let repository = &database.repository;
+    for email in ["ÉLODIE@example.com", " Élodie@EXAMPLE.COM "] {
+        let credentials = repository.get_user_credentials(email).unwrap();
+        assert_eq!(credentials.user_id, "existing");
+    }
+}
+
+#[test]
+fn test_sqlite_memory_database() {
+    // every connection of the pool would have its own database
+    for path in [":memory:", "file::memory:", "file:test?mode=memory"] {
+        let error = create_sqlite_pool(path, &PoolConfig::default()).unwrap_err();
+        assert_eq!(
+            error.code_name,
+            ERR_BACKEND_CONNECTION_STRING_NOT_FOUND.code_name
+        );
+    }
+    let single = PoolConfig {
+        max_size: 1,
+        ..PoolConfig::default()
+    };
+    assert!(create_sqlite_pool(":memory:", &single).is_ok());
+
+    // the connections of a shared cache see the same tables
+    let path = format!("file:{}?mode=memory&cache=shared", Uuid::new_v4());
+    let repository =
+        SqliteRepository::new(create_sqlite_pool(&path, &PoolConfig::default()).unwrap());
+    // the database is removed when its last connection is closed
+    let keep_alive = get_database_connection(repository.pool()).unwrap();
+    run_pending_migrations(&DatabaseRepository::Sqlite(repository.clone())).unwrap();
+    let user_id = repository
+        .register_user_email_password("memory@example.com", "hash")
+        .unwrap();
+    assert_eq!(
+        repository
+            .get_user_credentials("memory@example.com")
+            .unwrap()
+            .user_id,
+        user_id
+    );
+    drop(keep_alive);
+}
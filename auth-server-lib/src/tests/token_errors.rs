@@ -0,0 +1,63 @@
+use token_helper::error::TokenError;
+
+use crate::api::errors::*;
+
+#[test]
+fn test_token_error_codes() {
+    let cases = [
+        (TokenError::Expired, ERR_TOKEN_EXPIRED),
+        (TokenError::TooOld, ERR_TOKEN_EXPIRED),
+        (TokenError::NotYetValid, ERR_TOKEN_NOT_YET_VALID),
+        (TokenError::IssuedInFuture, ERR_TOKEN_NOT_YET_VALID),
+        (TokenError::InvalidIssuer, ERR_TOKEN_INVALID_ISSUER),
+        (TokenError::InvalidAudience, ERR_TOKEN_INVALID_AUDIENCE),
+        (TokenError::Revoked, ERR_TOKEN_REVOKED),
+        (
+            TokenError::MalformedHeader("no JWT key id".to_string()),
+            ERR_TOKEN_MALFORMED,
+        ),
+        (
+            TokenError::InvalidClaim("roles".to_string()),
+            ERR_TOKEN_MALFORMED,
+        ),
+        (
+            TokenError::Decryption("bad tag".to_string()),
+            ERR_INVALID_TOKEN,
+        ),
+        (
+            TokenError::ForbiddenClaim("act".to_string()),
+            ERR_INVALID_TOKEN,
+        ),
+        (
+            TokenError::InvalidSubject("invalid subject".to_string()),
+            ERR_TOKEN_INVALID_SUBJECT,
+        ),
+        (
+            TokenError::InvalidProof("the proof was already used".to_string()),
+            ERR_INVALID_DPOP_PROOF,
+        ),
+        (
+            TokenError::Encoding("bad key".to_string()),
+            ERR_TOKEN_CREATION_FAILED,
+        ),
+        (
+            TokenError::ReservedClaim("sub".to_string()),
+            ERR_TOKEN_CREATION_FAILED,
+        ),
+    ];
+    for (error, expected) in cases {
+        let details = ErrorDetails::from(error);
+        assert_eq!(details.code_name, expected.code_name);
+        assert_eq!(details.http_code, expected.http_code);
+    }
+}
+
+#[test]
+fn test_token_error_keeps_details() {
+    let details = ErrorDetails::from(TokenError::UnknownKey("2022-10".to_string()));
+    assert_eq!(details.code_name, ERR_INVALID_TOKEN.code_name);
+    assert_eq!(
+        details.internal_error,
+        Some("unknown key id 2022-10".to_string())
+    );
+}
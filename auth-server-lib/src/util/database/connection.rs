@@ -1,13 +1,73 @@
-use crate::api::errors::*;
-use dboilerplate::util::configuration;
-use diesel::prelude::*;
-
-pub(crate) fn get_database_connection() -> Result<PgConnection, ErrorDetails> {
-    let config = configuration::get_config(None, None);
-    let database_url: String = config
-        .extract_inner("DATABASE_URL")
-        .map_err(|e| ERR_BACKEND_CONNECTION_STRING_NOT_FOUND.with_internal_error(e.to_string()))?;
-    let connection = PgConnection::establish(&database_url)
-        .map_err(|e| ERR_BACKEND_CONNECTION_FAILED.with_internal_error(e.to_string()))?;
-    Ok(connection)
-}
+use crate::api::{
+    errors::*,
+    repository::{DatabaseRepository, Repository},
+};
+use dboilerplate::util::{
+    configuration,
+    pool::{create_pool, PoolConfig},
+};
+use diesel::{
+    prelude::*,
+    r2d2::{ConnectionManager, Pool, PooledConnection, R2D2Connection},
+};
+
+use crate::util::database::postgres::PgRepository;
+#[cfg(feature = "sqlite")]
+use crate::util::database::sqlite::{create_sqlite_pool, SqliteRepository};
+
+/// the pool of connections to the database, created once at startup
+pub type DatabasePool = Pool<ConnectionManager<PgConnection>>;
+
+/// creates the repository of the database of `DATABASE_URL` with the `DATABASE_POOL_*` settings,
+/// the scheme of the url selects the database: `postgres://` (or `postgresql://`)
+/// and `sqlite://<path>` with the `sqlite` feature
+pub(crate) fn create_database_repository() -> Result<DatabaseRepository, ErrorDetails> {
+    let config = configuration::get_config(None, None);
+    let database_url: String = config
+        .extract_inner("DATABASE_URL")
+        .map_err(|e| ERR_BACKEND_CONNECTION_STRING_NOT_FOUND.with_internal_error(e.to_string()))?;
+    let pool_config = PoolConfig::from_config(&config)
+        .map_err(|e| ERR_BACKEND_CONNECTION_STRING_NOT_FOUND.with_internal_error(e))?;
+    // the url is not in the errors, it may contain a password
+    let (scheme, _) = database_url.split_once("://").ok_or_else(|| {
+        ERR_BACKEND_CONNECTION_STRING_NOT_FOUND
+            .with_internal_error("DATABASE_URL has no scheme".to_string())
+    })?;
+    match scheme {
+        "postgres" | "postgresql" => Ok(DatabaseRepository::Postgres(PgRepository::new(
+            create_pool(&database_url, &pool_config),
+        ))),
+        #[cfg(feature = "sqlite")]
+        "sqlite" => Ok(DatabaseRepository::Sqlite(SqliteRepository::new(
+            create_sqlite_pool(&database_url["sqlite://".len()..], &pool_config)?,
+        ))),
+        #[cfg(not(feature = "sqlite"))]
+        "sqlite" => Err(ERR_BACKEND_CONNECTION_STRING_NOT_FOUND
+            .with_internal_error("the SQLite databases require the sqlite feature".to_string())),
+        other => Err(ERR_BACKEND_CONNECTION_STRING_NOT_FOUND
+            .with_internal_error(format!("unsupported database scheme {}", other))),
+    }
+}
+
+/// takes a connection from the pool, when every connection is in use
+/// it waits for one until the connection timeout of the pool
+pub(crate) fn get_database_connection<C: R2D2Connection + 'static>(
+    pool: &Pool<ConnectionManager<C>>,
+) -> Result<PooledConnection<ConnectionManager<C>>, ErrorDetails> {
+    pool.get()
+        .map_err(|e| ERR_BACKEND_CONNECTION_FAILED.with_internal_error(e.to_string()))
+}
+
+/// runs the (blocking) calls to the repository on the blocking threads of the runtime,
+/// so the queries do not stall the async workers
+pub(crate) async fn with_repository<R, T, F>(repository: &R, f: F) -> Result<T, ErrorDetails>
+where
+    R: Repository,
+    T: Send + 'static,
+    F: FnOnce(&R) -> Result<T, ErrorDetails> + Send + 'static,
+{
+    let repository = repository.clone();
+    tokio::task::spawn_blocking(move || f(&repository))
+        .await
+        .map_err(|e| ERR_UNKNOWN_INTERNAL_ERROR.with_internal_error(e.to_string()))?
+}
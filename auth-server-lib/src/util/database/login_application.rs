@@ -0,0 +1,73 @@
+use crate::api::errors::*;
+use crate::schema::login_applications;
+use diesel::{prelude::*, Queryable};
+
+#[derive(Queryable, Clone)]
+pub struct LoginApplication {
+    pub audience: String,
+    // argon2 hash of the secret used by the application to authenticate itself
+    pub client_secret_hash: Option<String>,
+    // the tokens of the application use a pairwise subject instead of the user id
+    pub pairwise_subject: bool,
+    // thumbprint of the client certificate used to authenticate with mutual TLS
+    pub certificate_thumbprint: Option<String>,
+}
+
+pub(crate) fn get_login_application(
+    connection: &mut PgConnection,
+    application_id: &uuid::Uuid,
+) -> Result<LoginApplication, ErrorDetails> {
+    let result = login_applications::table
+        .filter(login_applications::id.eq(application_id))
+        .select((
+            login_applications::audience,
+            login_applications::client_secret_hash,
+            login_applications::pairwise_subject,
+            login_applications::certificate_thumbprint,
+        ))
+        .get_result::<LoginApplication>(connection);
+    match result {
+        Ok(application) => Ok(application),
+        Err(e) => Err(ERR_DATABASE_RESOURCE_NOT_FOUND.with_internal_error(e.to_string())),
+    }
+}
+
+/// returns the registered application of an audience,
+/// an audience shared by several applications is rejected as ambiguous
+pub(crate) fn get_login_application_by_audience(
+    connection: &mut PgConnection,
+    audience: &str,
+) -> Result<(uuid::Uuid, LoginApplication), ErrorDetails> {
+    let result = login_applications::table
+        .filter(login_applications::audience.eq(audience))
+        .select((
+            login_applications::id,
+            (
+                login_applications::audience,
+                login_applications::client_secret_hash,
+                login_applications::pairwise_subject,
+                login_applications::certificate_thumbprint,
+            ),
+        ))
+        .limit(2)
+        .load::<(uuid::Uuid, LoginApplication)>(connection)
+        .map_err(|e| ERR_BACKEND_QUERY_FAILED.with_internal_error(e.to_string()))?;
+    single_application(audience, result)
+}
+
+/// returns the only application found for the audience
+pub(crate) fn single_application(
+    audience: &str,
+    applications: impl IntoIterator<Item = (uuid::Uuid, LoginApplication)>,
+) -> Result<(uuid::Uuid, LoginApplication), ErrorDetails> {
+    let mut applications = applications.into_iter();
+    match (applications.next(), applications.next()) {
+        (Some(application), None) => Ok(application),
+        (None, _) => Err(ERR_DATABASE_RESOURCE_NOT_FOUND
+            .with_internal_error(format!("no application for the audience {}", audience))),
+        (Some(_), Some(_)) => Err(ERR_DATABASE_RESOURCE_NOT_FOUND.with_internal_error(format!(
+            "several applications for the audience {}",
+            audience
+        ))),
+    }
+}
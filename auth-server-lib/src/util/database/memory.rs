@@ -0,0 +1,248 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, MutexGuard},
+    time::SystemTime,
+};
+
+use uuid::Uuid;
+
+use crate::{
+    api::{
+        errors::*,
+        repository::{ApplicationRepository, TokenRepository, UserRepository},
+    },
+    util::database::{
+        login_application::{single_application, LoginApplication},
+        refresh_token::{NewRefreshToken, RefreshTokenRecord},
+        user_email::{
+            generate_user_id, normalize_email, user_id_exhausted, UserCredentialsRecord,
+            USER_ID_ATTEMPTS,
+        },
+    },
+};
+
+/// a repository kept in memory, for the tests and the local development,
+/// the clones share the same storage and nothing is kept after the process exits
+#[derive(Clone, Default)]
+pub struct InMemoryRepository {
+    storage: Arc<Mutex<Storage>>,
+}
+
+#[derive(Default)]
+struct Storage {
+    // the credentials of the users by normalized email
+    users: HashMap<String, UserCredentialsRecord>,
+    applications: HashMap<Uuid, LoginApplication>,
+    // the user and the application of the pairwise subjects
+    pairwise_subjects: HashMap<String, (String, Uuid)>,
+    // the refresh tokens by hash
+    refresh_tokens: HashMap<String, RefreshTokenRecord>,
+    // the revoked token ids and their expiration time
+    revoked_tokens: HashMap<String, SystemTime>,
+}
+
+impl InMemoryRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// registers an application (there is no endpoint to create them)
+    pub fn insert_login_application(&self, application_id: Uuid, application: LoginApplication) {
+        self.storage()
+            .applications
+            .insert(application_id, application);
+    }
+
+    // a panic while the lock was held can not leave the maps half updated
+    fn storage(&self) -> MutexGuard<'_, Storage> {
+        self.storage.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl UserRepository for InMemoryRepository {
+    fn get_user_credentials(&self, email: &str) -> Result<UserCredentialsRecord, ErrorDetails> {
+        let email_normalized = normalize_email(email);
+        self.storage()
+            .users
+            .get(&email_normalized)
+            .cloned()
+            .ok_or_else(|| {
+                ERR_DATABASE_RESOURCE_NOT_FOUND.with_internal_error(format!("no user {}", email))
+            })
+    }
+
+    fn register_user_email_password(
+        &self,
+        email: &str,
+        password_hash: &str,
+    ) -> Result<String, ErrorDetails> {
+        let email_normalized = normalize_email(email);
+        let mut storage = self.storage();
+        if storage.users.contains_key(&email_normalized) {
+            return Err(
+                ERR_DATABASE_RECORD_EXISTS.with_internal_error("email already in use".to_string())
+            );
+        }
+        for _ in 0..USER_ID_ATTEMPTS {
+            let user_id = generate_user_id();
+            if storage.users.values().any(|user| user.user_id == user_id) {
+                continue;
+            }
+            storage.users.insert(
+                email_normalized,
+                UserCredentialsRecord {
+                    user_id: user_id.clone(),
+                    password_hash: password_hash.to_string(),
+                },
+            );
+            return Ok(user_id);
+        }
+        Err(user_id_exhausted())
+    }
+
+    fn insert_pairwise_subject(
+        &self,
+        subject: &str,
+        user_id: &str,
+        application_id: &Uuid,
+    ) -> Result<(), ErrorDetails> {
+        self.storage()
+            .pairwise_subjects
+            .entry(subject.to_string())
+            .or_insert_with(|| (user_id.to_string(), *application_id));
+        Ok(())
+    }
+
+    fn get_pairwise_subject_user_id(
+        &self,
+        subject: &str,
+        audience: &str,
+    ) -> Result<Option<String>, ErrorDetails> {
+        let storage = self.storage();
+        Ok(storage
+            .pairwise_subjects
+            .get(subject)
+            .filter(|(_, application_id)| {
+                storage
+                    .applications
+                    .get(application_id)
+                    .is_some_and(|application| application.audience == audience)
+            })
+            .map(|(user_id, _)| user_id.clone()))
+    }
+}
+
+impl ApplicationRepository for InMemoryRepository {
+    fn get_login_application(
+        &self,
+        application_id: &Uuid,
+    ) -> Result<LoginApplication, ErrorDetails> {
+        self.storage()
+            .applications
+            .get(application_id)
+            .cloned()
+            .ok_or_else(|| {
+                ERR_DATABASE_RESOURCE_NOT_FOUND
+                    .with_internal_error(format!("no application {}", application_id))
+            })
+    }
+
+    fn get_login_application_by_audience(
+        &self,
+        audience: &str,
+    ) -> Result<(Uuid, LoginApplication), ErrorDetails> {
+        let storage = self.storage();
+        let applications = storage
+            .applications
+            .iter()
+            .filter(|(_, application)| application.audience == audience)
+            .map(|(id, application)| (*id, application.clone()));
+        single_application(audience, applications)
+    }
+}
+
+impl TokenRepository for InMemoryRepository {
+    fn get_refresh_token(&self, token_hash: &str) -> Result<RefreshTokenRecord, ErrorDetails> {
+        self.storage()
+            .refresh_tokens
+            .get(token_hash)
+            .cloned()
+            .ok_or_else(|| {
+                ERR_INVALID_REFRESH_TOKEN.with_internal_error("unknown refresh token".to_string())
+            })
+    }
+
+    fn insert_refresh_token(&self, new_token: NewRefreshToken) -> Result<(), ErrorDetails> {
+        let mut storage = self.storage();
+        if storage.refresh_tokens.contains_key(new_token.token_hash) {
+            return Err(ERR_DATABASE_TRANSACTION_FAILED
+                .with_internal_error("duplicate refresh token".to_string()));
+        }
+        storage.refresh_tokens.insert(
+            new_token.token_hash.to_string(),
+            refresh_token_record(new_token),
+        );
+        Ok(())
+    }
+
+    fn rotate_refresh_token(
+        &self,
+        old_token_hash: &str,
+        new_token: NewRefreshToken,
+    ) -> Result<bool, ErrorDetails> {
+        let mut storage = self.storage();
+        match storage.refresh_tokens.get_mut(old_token_hash) {
+            Some(old_token) if !old_token.used => old_token.used = true,
+            _ => return Ok(false),
+        }
+        storage.refresh_tokens.insert(
+            new_token.token_hash.to_string(),
+            refresh_token_record(new_token),
+        );
+        Ok(true)
+    }
+
+    fn revoke_refresh_token_family(&self, family_id: &Uuid) -> Result<(), ErrorDetails> {
+        self.storage()
+            .refresh_tokens
+            .values_mut()
+            .filter(|token| token.family_id == *family_id)
+            .for_each(|token| token.revoked = true);
+        Ok(())
+    }
+
+    fn insert_revoked_token(
+        &self,
+        jwt_id: &str,
+        expires_at: SystemTime,
+    ) -> Result<(), ErrorDetails> {
+        let mut storage = self.storage();
+        let now = SystemTime::now();
+        storage
+            .revoked_tokens
+            .retain(|_, expires_at| *expires_at >= now);
+        storage
+            .revoked_tokens
+            .entry(jwt_id.to_string())
+            .or_insert(expires_at);
+        Ok(())
+    }
+
+    fn get_revoked_token(&self, jwt_id: &str) -> Result<Option<SystemTime>, ErrorDetails> {
+        Ok(self.storage().revoked_tokens.get(jwt_id).copied())
+    }
+}
+
+fn refresh_token_record(new_token: NewRefreshToken) -> RefreshTokenRecord {
+    RefreshTokenRecord {
+        family_id: *new_token.family_id,
+        user_id: new_token.user_id.to_string(),
+        application_id: *new_token.application_id,
+        expires_at: new_token.expires_at,
+        used: false,
+        revoked: false,
+        auth_time: new_token.auth_time,
+        dpop_jkt: new_token.dpop_jkt.map(str::to_string),
+        certificate_thumbprint: new_token.certificate_thumbprint.map(str::to_string),
+    }
+}
@@ -0,0 +1,116 @@
+use std::collections::HashSet;
+
+use dboilerplate::util::configuration;
+use diesel::{backend::Backend, migration::MigrationSource};
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+
+use crate::{
+    api::{errors::*, repository::DatabaseRepository},
+    util::database::connection::get_database_connection,
+};
+
+/// the configuration key of the flag applying the pending migrations at startup
+const MIGRATE_ON_START_KEY: &str = "DATABASE_MIGRATE_ON_START";
+
+/// the migrations of the Postgres databases, built in the binary
+const POSTGRES_MIGRATIONS: EmbeddedMigrations = embed_migrations!("../migrations");
+/// the migrations of the SQLite databases, built in the binary
+#[cfg(feature = "sqlite")]
+pub(crate) const SQLITE_MIGRATIONS: EmbeddedMigrations = embed_migrations!("../migrations_sqlite");
+
+/// a migration of the database and whether it is applied
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationStatus {
+    /// the name of the directory of the migration, starting with its version
+    pub name: String,
+    pub applied: bool,
+}
+
+/// applies the migrations missing in the database, in order,
+/// returns the names of the applied migrations
+pub(crate) fn run_pending_migrations(
+    repository: &DatabaseRepository,
+) -> Result<Vec<String>, ErrorDetails> {
+    match repository {
+        DatabaseRepository::Postgres(repository) => {
+            let connection = &mut get_database_connection(repository.pool())?;
+            run_pending(connection, POSTGRES_MIGRATIONS)
+        }
+        #[cfg(feature = "sqlite")]
+        DatabaseRepository::Sqlite(repository) => {
+            let connection = &mut get_database_connection(repository.pool())?;
+            run_pending(connection, SQLITE_MIGRATIONS)
+        }
+    }
+}
+
+/// the embedded migrations, in order, and whether they are applied to the database
+pub(crate) fn migration_status(
+    repository: &DatabaseRepository,
+) -> Result<Vec<MigrationStatus>, ErrorDetails> {
+    match repository {
+        DatabaseRepository::Postgres(repository) => {
+            let connection = &mut get_database_connection(repository.pool())?;
+            status(connection, POSTGRES_MIGRATIONS)
+        }
+        #[cfg(feature = "sqlite")]
+        DatabaseRepository::Sqlite(repository) => {
+            let connection = &mut get_database_connection(repository.pool())?;
+            status(connection, SQLITE_MIGRATIONS)
+        }
+    }
+}
+
+/// reads `DATABASE_MIGRATE_ON_START`, false when it is not set
+pub(crate) fn migrate_on_start() -> Result<bool, ErrorDetails> {
+    let config = configuration::get_config(None, None);
+    match config.find_value(MIGRATE_ON_START_KEY) {
+        Ok(_) => config
+            .extract_inner::<bool>(MIGRATE_ON_START_KEY)
+            .map_err(|e| ERR_DATABASE_MIGRATION_FAILED.with_internal_error(e.to_string())),
+        Err(_) => Ok(false),
+    }
+}
+
+fn run_pending<DB: Backend>(
+    connection: &mut impl MigrationHarness<DB>,
+    migrations: EmbeddedMigrations,
+) -> Result<Vec<String>, ErrorDetails> {
+    let pending = connection
+        .pending_migrations(migrations)
+        .map_err(|e| ERR_DATABASE_MIGRATION_FAILED.with_internal_error(e.to_string()))?;
+    let mut applied = Vec::with_capacity(pending.len());
+    for migration in pending {
+        // every migration runs in its own transaction,
+        // the migrations applied before a failure are kept
+        connection.run_migration(migration.as_ref()).map_err(|e| {
+            ERR_DATABASE_MIGRATION_FAILED.with_internal_error(format!(
+                "migration {}: {}",
+                migration.name(),
+                e
+            ))
+        })?;
+        applied.push(migration.name().to_string());
+    }
+    Ok(applied)
+}
+
+fn status<DB: Backend>(
+    connection: &mut impl MigrationHarness<DB>,
+    migrations: EmbeddedMigrations,
+) -> Result<Vec<MigrationStatus>, ErrorDetails> {
+    let applied = connection
+        .applied_migrations()
+        .map_err(|e| ERR_DATABASE_MIGRATION_FAILED.with_internal_error(e.to_string()))?
+        .into_iter()
+        .collect::<HashSet<_>>();
+    let migrations = MigrationSource::<DB>::migrations(&migrations)
+        .map_err(|e| ERR_DATABASE_MIGRATION_FAILED.with_internal_error(e.to_string()))?;
+    Ok(migrations
+        .iter()
+        .map(|migration| MigrationStatus {
+            name: migration.name().to_string(),
+            applied: applied.contains(&migration.name().version()),
+        })
+        .collect())
+}
@@ -0,0 +1,47 @@
+use crate::api::errors::*;
+use crate::schema::{login_applications, pairwise_subjects};
+use diesel::prelude::*;
+
+#[derive(Insertable)]
+#[diesel(table_name = pairwise_subjects)]
+struct NewPairwiseSubject<'r> {
+    pub subject: &'r str,
+    pub user_id: &'r str,
+    pub application_id: &'r uuid::Uuid,
+}
+
+/// records the pairwise subject of an user for the application,
+/// the subject is stable so it is only stored the first time
+pub(crate) fn insert_pairwise_subject(
+    connection: &mut PgConnection,
+    subject: &str,
+    user_id: &str,
+    application_id: &uuid::Uuid,
+) -> Result<(), ErrorDetails> {
+    diesel::insert_into(pairwise_subjects::table)
+        .values(NewPairwiseSubject {
+            subject,
+            user_id,
+            application_id,
+        })
+        .on_conflict_do_nothing()
+        .execute(connection)
+        .map(|_| ())
+        .map_err(|e| ERR_BACKEND_QUERY_FAILED.with_internal_error(e.to_string()))
+}
+
+/// returns the user id behind the pairwise subject of an application with the audience
+pub(crate) fn get_pairwise_subject_user_id(
+    connection: &mut PgConnection,
+    subject: &str,
+    audience: &str,
+) -> Result<Option<String>, ErrorDetails> {
+    pairwise_subjects::table
+        .inner_join(login_applications::table)
+        .filter(pairwise_subjects::subject.eq(subject))
+        .filter(login_applications::audience.eq(audience))
+        .select(pairwise_subjects::user_id)
+        .first::<String>(connection)
+        .optional()
+        .map_err(|e| ERR_BACKEND_QUERY_FAILED.with_internal_error(e.to_string()))
+}
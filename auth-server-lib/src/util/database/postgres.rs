@@ -0,0 +1,133 @@
+use std::time::SystemTime;
+
+use uuid::Uuid;
+
+use crate::{
+    api::{
+        errors::ErrorDetails,
+        repository::{ApplicationRepository, TokenRepository, UserRepository},
+    },
+    util::database::{
+        connection::{get_database_connection, DatabasePool},
+        login_application::{self, LoginApplication},
+        pairwise_subject,
+        refresh_token::{self, NewRefreshToken, RefreshTokenRecord},
+        revoked_token,
+        user_email::{self, UserCredentialsRecord},
+    },
+};
+
+/// the repository backed by the Postgres database,
+/// every call takes its own connection from the pool
+#[derive(Clone)]
+pub struct PgRepository {
+    pool: DatabasePool,
+}
+
+impl PgRepository {
+    pub fn new(pool: DatabasePool) -> Self {
+        Self { pool }
+    }
+
+    pub(crate) fn pool(&self) -> &DatabasePool {
+        &self.pool
+    }
+}
+
+impl UserRepository for PgRepository {
+    fn get_user_credentials(&self, email: &str) -> Result<UserCredentialsRecord, ErrorDetails> {
+        let connection = &mut get_database_connection(&self.pool)?;
+        user_email::get_user_credentials(connection, email)
+    }
+
+    fn register_user_email_password(
+        &self,
+        email: &str,
+        password_hash: &str,
+    ) -> Result<String, ErrorDetails> {
+        let connection = &mut get_database_connection(&self.pool)?;
+        user_email::register_new_user_email_password(
+            connection,
+            email,
+            password_hash,
+            user_email::generate_user_id,
+        )
+    }
+
+    fn insert_pairwise_subject(
+        &self,
+        subject: &str,
+        user_id: &str,
+        application_id: &Uuid,
+    ) -> Result<(), ErrorDetails> {
+        let connection = &mut get_database_connection(&self.pool)?;
+        pairwise_subject::insert_pairwise_subject(connection, subject, user_id, application_id)
+    }
+
+    fn get_pairwise_subject_user_id(
+        &self,
+        subject: &str,
+        audience: &str,
+    ) -> Result<Option<String>, ErrorDetails> {
+        let connection = &mut get_database_connection(&self.pool)?;
+        pairwise_subject::get_pairwise_subject_user_id(connection, subject, audience)
+    }
+}
+
+impl ApplicationRepository for PgRepository {
+    fn get_login_application(
+        &self,
+        application_id: &Uuid,
+    ) -> Result<LoginApplication, ErrorDetails> {
+        let connection = &mut get_database_connection(&self.pool)?;
+        login_application::get_login_application(connection, application_id)
+    }
+
+    fn get_login_application_by_audience(
+        &self,
+        audience: &str,
+    ) -> Result<(Uuid, LoginApplication), ErrorDetails> {
+        let connection = &mut get_database_connection(&self.pool)?;
+        login_application::get_login_application_by_audience(connection, audience)
+    }
+}
+
+impl TokenRepository for PgRepository {
+    fn get_refresh_token(&self, token_hash: &str) -> Result<RefreshTokenRecord, ErrorDetails> {
+        let connection = &mut get_database_connection(&self.pool)?;
+        refresh_token::get_refresh_token(connection, token_hash)
+    }
+
+    fn insert_refresh_token(&self, new_token: NewRefreshToken) -> Result<(), ErrorDetails> {
+        let connection = &mut get_database_connection(&self.pool)?;
+        refresh_token::insert_refresh_token(connection, new_token)
+    }
+
+    fn rotate_refresh_token(
+        &self,
+        old_token_hash: &str,
+        new_token: NewRefreshToken,
+    ) -> Result<bool, ErrorDetails> {
+        let connection = &mut get_database_connection(&self.pool)?;
+        refresh_token::rotate_refresh_token(connection, old_token_hash, new_token)
+    }
+
+    fn revoke_refresh_token_family(&self, family_id: &Uuid) -> Result<(), ErrorDetails> {
+        let connection = &mut get_database_connection(&self.pool)?;
+        refresh_token::revoke_refresh_token_family(connection, family_id)
+    }
+
+    fn insert_revoked_token(
+        &self,
+        jwt_id: &str,
+        expires_at: SystemTime,
+    ) -> Result<(), ErrorDetails> {
+        let connection = &mut get_database_connection(&self.pool)?;
+        revoked_token::insert_revoked_token(connection, jwt_id, expires_at)
+    }
+
+    fn get_revoked_token(&self, jwt_id: &str) -> Result<Option<SystemTime>, ErrorDetails> {
+        let connection = &mut get_database_connection(&self.pool)?;
+        revoked_token::get_revoked_token(connection, jwt_id)
+    }
+}
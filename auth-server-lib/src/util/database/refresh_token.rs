@@ -0,0 +1,114 @@
+use std::time::SystemTime;
+
+use crate::api::errors::*;
+use crate::schema::refresh_tokens;
+use diesel::{prelude::*, Queryable};
+
+#[derive(Queryable, Clone)]
+pub struct RefreshTokenRecord {
+    pub family_id: uuid::Uuid,
+    pub user_id: String,
+    pub application_id: uuid::Uuid,
+    pub expires_at: SystemTime,
+    pub used: bool,
+    pub revoked: bool,
+    pub auth_time: SystemTime,
+    pub dpop_jkt: Option<String>,
+    pub certificate_thumbprint: Option<String>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = refresh_tokens)]
+pub struct NewRefreshToken<'r> {
+    pub token_hash: &'r str,
+    pub family_id: &'r uuid::Uuid,
+    pub user_id: &'r str,
+    pub application_id: &'r uuid::Uuid,
+    pub expires_at: SystemTime,
+    // the time of the login that started the family
+    pub auth_time: SystemTime,
+    // the thumbprint of the DPoP key the family is bound to
+    pub dpop_jkt: Option<&'r str>,
+    // the thumbprint of the client certificate the family is bound to
+    pub certificate_thumbprint: Option<&'r str>,
+}
+
+pub(crate) fn get_refresh_token(
+    connection: &mut PgConnection,
+    token_hash: &str,
+) -> Result<RefreshTokenRecord, ErrorDetails> {
+    let result = refresh_tokens::table
+        .filter(refresh_tokens::token_hash.eq(token_hash))
+        .select((
+            refresh_tokens::family_id,
+            refresh_tokens::user_id,
+            refresh_tokens::application_id,
+            refresh_tokens::expires_at,
+            refresh_tokens::used,
+            refresh_tokens::revoked,
+            refresh_tokens::auth_time,
+            refresh_tokens::dpop_jkt,
+            refresh_tokens::certificate_thumbprint,
+        ))
+        .get_result::<RefreshTokenRecord>(connection);
+    match result {
+        Ok(record) => Ok(record),
+        Err(diesel::result::Error::NotFound) => {
+            Err(ERR_INVALID_REFRESH_TOKEN.with_internal_error("unknown refresh token".to_string()))
+        }
+        Err(e) => Err(ERR_BACKEND_QUERY_FAILED.with_internal_error(e.to_string())),
+    }
+}
+
+pub(crate) fn insert_refresh_token(
+    connection: &mut PgConnection,
+    new_token: NewRefreshToken,
+) -> Result<(), ErrorDetails> {
+    diesel::insert_into(refresh_tokens::table)
+        .values(&new_token)
+        .execute(connection)
+        .map_err(|e| ERR_DATABASE_TRANSACTION_FAILED.with_internal_error(e.to_string()))?;
+    Ok(())
+}
+
+/// marks the old token as used and stores its replacement in the same family
+/// returns false if the old token was already used (by a concurrent refresh)
+pub(crate) fn rotate_refresh_token(
+    connection: &mut PgConnection,
+    old_token_hash: &str,
+    new_token: NewRefreshToken,
+) -> Result<bool, ErrorDetails> {
+    let transaction_result =
+        connection.transaction::<_, diesel::result::Error, _>(|connection: &mut PgConnection| {
+            let updated = diesel::update(
+                refresh_tokens::table
+                    .filter(refresh_tokens::token_hash.eq(old_token_hash))
+                    .filter(refresh_tokens::used.eq(false)),
+            )
+            .set(refresh_tokens::used.eq(true))
+            .execute(connection)?;
+            if updated == 0 {
+                return Ok(false);
+            }
+            diesel::insert_into(refresh_tokens::table)
+                .values(&new_token)
+                .execute(connection)?;
+            Ok(true)
+        });
+    match transaction_result {
+        Ok(rotated) => Ok(rotated),
+        Err(e) => Err(ERR_DATABASE_TRANSACTION_FAILED.with_internal_error(e.to_string())),
+    }
+}
+
+/// revokes every refresh token of the family
+pub(crate) fn revoke_refresh_token_family(
+    connection: &mut PgConnection,
+    family_id: &uuid::Uuid,
+) -> Result<(), ErrorDetails> {
+    diesel::update(refresh_tokens::table.filter(refresh_tokens::family_id.eq(family_id)))
+        .set(refresh_tokens::revoked.eq(true))
+        .execute(connection)
+        .map_err(|e| ERR_DATABASE_TRANSACTION_FAILED.with_internal_error(e.to_string()))?;
+    Ok(())
+}
@@ -0,0 +1,50 @@
+use std::time::SystemTime;
+
+use crate::api::errors::*;
+use crate::schema::revoked_tokens;
+use diesel::prelude::*;
+
+#[derive(Insertable)]
+#[diesel(table_name = revoked_tokens)]
+struct RevokedToken<'r> {
+    pub jwt_id: &'r str,
+    pub expires_at: SystemTime,
+}
+
+/// stores the id of a revoked token until it expires
+/// the tokens that already expired are removed from the table
+pub(crate) fn insert_revoked_token(
+    connection: &mut PgConnection,
+    jwt_id: &str,
+    expires_at: SystemTime,
+) -> Result<(), ErrorDetails> {
+    let transaction_result =
+        connection.transaction::<_, diesel::result::Error, _>(|connection: &mut PgConnection| {
+            diesel::delete(
+                revoked_tokens::table.filter(revoked_tokens::expires_at.lt(SystemTime::now())),
+            )
+            .execute(connection)?;
+            diesel::insert_into(revoked_tokens::table)
+                .values(RevokedToken { jwt_id, expires_at })
+                .on_conflict_do_nothing()
+                .execute(connection)?;
+            Ok(())
+        });
+    match transaction_result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(ERR_DATABASE_TRANSACTION_FAILED.with_internal_error(e.to_string())),
+    }
+}
+
+/// returns the expiration time of the token if it was revoked
+pub(crate) fn get_revoked_token(
+    connection: &mut PgConnection,
+    jwt_id: &str,
+) -> Result<Option<SystemTime>, ErrorDetails> {
+    revoked_tokens::table
+        .filter(revoked_tokens::jwt_id.eq(jwt_id))
+        .select(revoked_tokens::expires_at)
+        .get_result::<SystemTime>(connection)
+        .optional()
+        .map_err(|e| ERR_BACKEND_QUERY_FAILED.with_internal_error(e.to_string()))
+}
@@ -0,0 +1,421 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use dboilerplate::util::pool::{create_pool_with_customizer, PoolConfig};
+use diesel::{
+    connection::SimpleConnection,
+    prelude::*,
+    r2d2::{ConnectionManager, CustomizeConnection, Pool},
+};
+use uuid::Uuid;
+
+use crate::{
+    api::{
+        errors::*,
+        repository::{ApplicationRepository, TokenRepository, UserRepository},
+    },
+    schema_sqlite::{
+        login_applications, pairwise_subjects, refresh_tokens, revoked_tokens, user_emails,
+        user_passwords, users,
+    },
+    util::database::{
+        connection::get_database_connection,
+        login_application::{single_application, LoginApplication},
+        refresh_token::{NewRefreshToken, RefreshTokenRecord},
+        user_email::{
+            generate_user_id, normalize_email, registration_error, user_id_exhausted,
+            UserCredentialsRecord, USER_ID_ATTEMPTS,
+        },
+    },
+};
+
+/// the pool of connections to a SQLite database
+pub type SqlitePool = Pool<ConnectionManager<SqliteConnection>>;
+
+/// creates the connection pool of the SQLite database file,
+/// the tables are created by the migrations of `migrations_sqlite`
+///
+/// every connection opens its own in-memory database, so an in-memory database
+/// requires a pool of a single connection or a shared cache (`file::memory:?cache=shared`)
+pub(crate) fn create_sqlite_pool(
+    path: &str,
+    pool_config: &PoolConfig,
+) -> Result<SqlitePool, ErrorDetails> {
+    if is_private_memory_database(path) && pool_config.max_size > 1 {
+        return Err(ERR_BACKEND_CONNECTION_STRING_NOT_FOUND.with_internal_error(
+            "an in-memory SQLite database requires DATABASE_POOL_MAX_SIZE = 1 or a shared cache"
+                .to_string(),
+        ));
+    }
+    let pragmas = SqlitePragmas {
+        busy_timeout: pool_config.connection_timeout,
+    };
+    Ok(create_pool_with_customizer(
+        path,
+        pool_config,
+        Box::new(pragmas),
+    ))
+}
+
+// an in-memory database that is not shared by the connections
+fn is_private_memory_database(path: &str) -> bool {
+    if path == ":memory:" {
+        return true;
+    }
+    match path.strip_prefix("file:") {
+        Some(uri) => {
+            let (name, query) = uri.split_once('?').unwrap_or((uri, ""));
+            let memory = name == ":memory:" || query.split('&').any(|p| p == "mode=memory");
+            memory && !query.split('&').any(|p| p == "cache=shared")
+        }
+        None => false,
+    }
+}
+
+// the settings of SQLite are per connection
+#[derive(Debug)]
+struct SqlitePragmas {
+    // how long a write waits for the lock of another connection
+    busy_timeout: Duration,
+}
+
+impl CustomizeConnection<SqliteConnection, diesel::r2d2::Error> for SqlitePragmas {
+    fn on_acquire(&self, connection: &mut SqliteConnection) -> Result<(), diesel::r2d2::Error> {
+        connection
+            .batch_execute(&format!(
+                "PRAGMA foreign_keys = ON; PRAGMA busy_timeout = {}; PRAGMA journal_mode = WAL;",
+                self.busy_timeout.as_millis()
+            ))
+            .map_err(diesel::r2d2::Error::QueryError)
+    }
+}
+
+/// the repository backed by a SQLite database, the uuids are stored as text
+/// and the times as unix timestamps (seconds)
+#[derive(Clone)]
+pub struct SqliteRepository {
+    pool: SqlitePool,
+}
+
+impl SqliteRepository {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    pub(crate) fn pool(&self) -> &SqlitePool {
+        &self.pool
+    }
+
+    /// registers the user in a single transaction, see
+    /// `user_email::register_new_user_email_password` of the Postgres databases
+    pub(crate) fn register_user(
+        &self,
+        email: &str,
+        password_hash: &str,
+        mut user_ids: impl FnMut() -> String,
+    ) -> Result<String, ErrorDetails> {
+        let connection = &mut get_database_connection(&self.pool)?;
+        let email_normalized = normalize_email(email);
+        for _ in 0..USER_ID_ATTEMPTS {
+            let new_user_id = user_ids();
+            let registered = connection
+                .immediate_transaction::<_, diesel::result::Error, _>(|connection| {
+                    // a taken id inserts nothing, the registration is tried again with another id
+                    let inserted = diesel::insert_or_ignore_into(users::table)
+                        .values(users::user_id.eq(&new_user_id))
+                        .execute(connection)?;
+                    if inserted == 0 {
+                        return Ok(false);
+                    }
+                    diesel::insert_into(user_emails::table)
+                        .values((
+                            user_emails::user_id.eq(&new_user_id),
+                            user_emails::email.eq(email.trim()),
+                            user_emails::email_normalized.eq(&email_normalized),
+                        ))
+                        .execute(connection)?;
+                    diesel::insert_into(user_passwords::table)
+                        .values((
+                            user_passwords::user_id.eq(&new_user_id),
+                            user_passwords::password_hash.eq(password_hash),
+                        ))
+                        .execute(connection)?;
+                    Ok(true)
+                })
+                .map_err(registration_error)?;
+            if registered {
+                return Ok(new_user_id);
+            }
+        }
+        Err(user_id_exhausted())
+    }
+}
+
+impl UserRepository for SqliteRepository {
+    fn get_user_credentials(&self, email: &str) -> Result<UserCredentialsRecord, ErrorDetails> {
+        let connection = &mut get_database_connection(&self.pool)?;
+        user_emails::table
+            .inner_join(user_passwords::table.on(user_emails::user_id.eq(user_passwords::user_id)))
+            .filter(user_emails::email_normalized.eq(normalize_email(email)))
+            .select((user_emails::user_id, user_passwords::password_hash))
+            .get_result::<UserCredentialsRecord>(connection)
+            .map_err(|e| ERR_DATABASE_RESOURCE_NOT_FOUND.with_internal_error(e.to_string()))
+    }
+
+    fn register_user_email_password(
+        &self,
+        email: &str,
+        password_hash: &str,
+    ) -> Result<String, ErrorDetails> {
+        self.register_user(email, password_hash, generate_user_id)
+    }
+
+    fn insert_pairwise_subject(
+        &self,
+        subject: &str,
+        user_id: &str,
+        application_id: &Uuid,
+    ) -> Result<(), ErrorDetails> {
+        let connection = &mut get_database_connection(&self.pool)?;
+        diesel::insert_or_ignore_into(pairwise_subjects::table)
+            .values((
+                pairwise_subjects::subject.eq(subject),
+                pairwise_subjects::user_id.eq(user_id),
+                pairwise_subjects::application_id.eq(application_id.to_string()),
+            ))
+            .execute(connection)
+            .map(|_| ())
+            .map_err(|e| ERR_BACKEND_QUERY_FAILED.with_internal_error(e.to_string()))
+    }
+
+    fn get_pairwise_subject_user_id(
+        &self,
+        subject: &str,
+        audience: &str,
+    ) -> Result<Option<String>, ErrorDetails> {
+        let connection = &mut get_database_connection(&self.pool)?;
+        pairwise_subjects::table
+            .inner_join(login_applications::table)
+            .filter(pairwise_subjects::subject.eq(subject))
+            .filter(login_applications::audience.eq(audience))
+            .select(pairwise_subjects::user_id)
+            .first::<String>(connection)
+            .optional()
+            .map_err(|e| ERR_BACKEND_QUERY_FAILED.with_internal_error(e.to_string()))
+    }
+}
+
+impl ApplicationRepository for SqliteRepository {
+    fn get_login_application(
+        &self,
+        application_id: &Uuid,
+    ) -> Result<LoginApplication, ErrorDetails> {
+        let connection = &mut get_database_connection(&self.pool)?;
+        login_applications::table
+            .filter(login_applications::id.eq(application_id.to_string()))
+            .select((
+                login_applications::audience,
+                login_applications::client_secret_hash,
+                login_applications::pairwise_subject,
+                login_applications::certificate_thumbprint,
+            ))
+            .get_result::<LoginApplication>(connection)
+            .map_err(|e| ERR_DATABASE_RESOURCE_NOT_FOUND.with_internal_error(e.to_string()))
+    }
+
+    fn get_login_application_by_audience(
+        &self,
+        audience: &str,
+    ) -> Result<(Uuid, LoginApplication), ErrorDetails> {
+        let connection = &mut get_database_connection(&self.pool)?;
+        let result = login_applications::table
+            .filter(login_applications::audience.eq(audience))
+            .select((
+                login_applications::id,
+                (
+                    login_applications::audience,
+                    login_applications::client_secret_hash,
+                    login_applications::pairwise_subject,
+                    login_applications::certificate_thumbprint,
+                ),
+            ))
+            .limit(2)
+            .load::<(String, LoginApplication)>(connection)
+            .map_err(|e| ERR_BACKEND_QUERY_FAILED.with_internal_error(e.to_string()))?;
+        let applications = result
+            .into_iter()
+            .map(|(id, application)| Ok((parse_uuid(&id)?, application)))
+            .collect::<Result<Vec<_>, ErrorDetails>>()?;
+        single_application(audience, applications)
+    }
+}
+
+type RefreshTokenRow = (
+    String,
+    String,
+    String,
+    i64,
+    bool,
+    bool,
+    i64,
+    Option<String>,
+    Option<String>,
+);
+
+impl TokenRepository for SqliteRepository {
+    fn get_refresh_token(&self, token_hash: &str) -> Result<RefreshTokenRecord, ErrorDetails> {
+        let connection = &mut get_database_connection(&self.pool)?;
+        let result = refresh_tokens::table
+            .filter(refresh_tokens::token_hash.eq(token_hash))
+            .select((
+                refresh_tokens::family_id,
+                refresh_tokens::user_id,
+                refresh_tokens::application_id,
+                refresh_tokens::expires_at,
+                refresh_tokens::used,
+                refresh_tokens::revoked,
+                refresh_tokens::auth_time,
+                refresh_tokens::dpop_jkt,
+                refresh_tokens::certificate_thumbprint,
+            ))
+            .get_result::<RefreshTokenRow>(connection);
+        let row = match result {
+            Ok(row) => row,
+            Err(diesel::result::Error::NotFound) => {
+                return Err(ERR_INVALID_REFRESH_TOKEN
+                    .with_internal_error("unknown refresh token".to_string()))
+            }
+            Err(e) => return Err(ERR_BACKEND_QUERY_FAILED.with_internal_error(e.to_string())),
+        };
+        let (
+            family_id,
+            user_id,
+            application_id,
+            expires_at,
+            used,
+            revoked,
+            auth_time,
+            dpop_jkt,
+            certificate_thumbprint,
+        ) = row;
+        Ok(RefreshTokenRecord {
+            family_id: parse_uuid(&family_id)?,
+            user_id,
+            application_id: parse_uuid(&application_id)?,
+            expires_at: from_unix_time(expires_at),
+            used,
+            revoked,
+            auth_time: from_unix_time(auth_time),
+            dpop_jkt,
+            certificate_thumbprint,
+        })
+    }
+
+    fn insert_refresh_token(&self, new_token: NewRefreshToken) -> Result<(), ErrorDetails> {
+        let connection = &mut get_database_connection(&self.pool)?;
+        insert_refresh_token(connection, &new_token)
+            .map_err(|e| ERR_DATABASE_TRANSACTION_FAILED.with_internal_error(e.to_string()))
+    }
+
+    fn rotate_refresh_token(
+        &self,
+        old_token_hash: &str,
+        new_token: NewRefreshToken,
+    ) -> Result<bool, ErrorDetails> {
+        let connection = &mut get_database_connection(&self.pool)?;
+        connection
+            .immediate_transaction::<_, diesel::result::Error, _>(|connection| {
+                let updated = diesel::update(
+                    refresh_tokens::table
+                        .filter(refresh_tokens::token_hash.eq(old_token_hash))
+                        .filter(refresh_tokens::used.eq(false)),
+                )
+                .set(refresh_tokens::used.eq(true))
+                .execute(connection)?;
+                if updated == 0 {
+                    return Ok(false);
+                }
+                insert_refresh_token(connection, &new_token)?;
+                Ok(true)
+            })
+            .map_err(|e| ERR_DATABASE_TRANSACTION_FAILED.with_internal_error(e.to_string()))
+    }
+
+    fn revoke_refresh_token_family(&self, family_id: &Uuid) -> Result<(), ErrorDetails> {
+        let connection = &mut get_database_connection(&self.pool)?;
+        diesel::update(
+            refresh_tokens::table.filter(refresh_tokens::family_id.eq(family_id.to_string())),
+        )
+        .set(refresh_tokens::revoked.eq(true))
+        .execute(connection)
+        .map(|_| ())
+        .map_err(|e| ERR_DATABASE_TRANSACTION_FAILED.with_internal_error(e.to_string()))
+    }
+
+    fn insert_revoked_token(
+        &self,
+        jwt_id: &str,
+        expires_at: SystemTime,
+    ) -> Result<(), ErrorDetails> {
+        let connection = &mut get_database_connection(&self.pool)?;
+        connection
+            .immediate_transaction::<_, diesel::result::Error, _>(|connection| {
+                diesel::delete(
+                    revoked_tokens::table
+                        .filter(revoked_tokens::expires_at.lt(to_unix_time(SystemTime::now()))),
+                )
+                .execute(connection)?;
+                diesel::insert_or_ignore_into(revoked_tokens::table)
+                    .values((
+                        revoked_tokens::jwt_id.eq(jwt_id),
+                        revoked_tokens::expires_at.eq(to_unix_time(expires_at)),
+                    ))
+                    .execute(connection)?;
+                Ok(())
+            })
+            .map_err(|e| ERR_DATABASE_TRANSACTION_FAILED.with_internal_error(e.to_string()))
+    }
+
+    fn get_revoked_token(&self, jwt_id: &str) -> Result<Option<SystemTime>, ErrorDetails> {
+        let connection = &mut get_database_connection(&self.pool)?;
+        revoked_tokens::table
+            .filter(revoked_tokens::jwt_id.eq(jwt_id))
+            .select(revoked_tokens::expires_at)
+            .get_result::<i64>(connection)
+            .optional()
+            .map(|expires_at| expires_at.map(from_unix_time))
+            .map_err(|e| ERR_BACKEND_QUERY_FAILED.with_internal_error(e.to_string()))
+    }
+}
+
+fn insert_refresh_token(
+    connection: &mut SqliteConnection,
+    new_token: &NewRefreshToken,
+) -> Result<(), diesel::result::Error> {
+    diesel::insert_into(refresh_tokens::table)
+        .values((
+            refresh_tokens::token_hash.eq(new_token.token_hash),
+            refresh_tokens::family_id.eq(new_token.family_id.to_string()),
+            refresh_tokens::user_id.eq(new_token.user_id),
+            refresh_tokens::application_id.eq(new_token.application_id.to_string()),
+            refresh_tokens::expires_at.eq(to_unix_time(new_token.expires_at)),
+            refresh_tokens::auth_time.eq(to_unix_time(new_token.auth_time)),
+            refresh_tokens::dpop_jkt.eq(new_token.dpop_jkt),
+            refresh_tokens::certificate_thumbprint.eq(new_token.certificate_thumbprint),
+        ))
+        .execute(connection)
+        .map(|_| ())
+}
+
+fn parse_uuid(value: &str) -> Result<Uuid, ErrorDetails> {
+    Uuid::parse_str(value).map_err(|e| ERR_BACKEND_QUERY_FAILED.with_internal_error(e.to_string()))
+}
+
+fn to_unix_time(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+fn from_unix_time(seconds: i64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(seconds.max(0) as u64)
+}
@@ -1,12 +1,19 @@
 use crate::api::errors::*;
 use crate::schema::{user_emails, user_passwords, users};
-use diesel::{prelude::*, Queryable};
+use diesel::{
+    prelude::*,
+    result::{DatabaseErrorKind, Error},
+    Queryable,
+};
 use rand::Rng;
 
-#[derive(Queryable)]
-pub(crate) struct UserCredentials {
+/// the attempts to register an user with a new random id,
+/// a generated id is already used only by bad luck so a few attempts are enough
+pub(crate) const USER_ID_ATTEMPTS: usize = 3;
+
+#[derive(Queryable, Clone)]
+pub struct UserCredentialsRecord {
     pub user_id: String,
-    pub user_email: String,
     pub password_hash: String,
 }
 
@@ -28,69 +35,104 @@ struct UserPassword<'r> {
 struct UserEmail<'r> {
     pub user_id: &'r str,
     pub email: &'r str,
+    pub email_normalized: &'r str,
 }
 
 pub(crate) fn get_user_credentials(
     connection: &mut PgConnection,
     user_email: &str,
-) -> Result<UserCredentials, ErrorDetails> {
+) -> Result<UserCredentialsRecord, ErrorDetails> {
     let query = user_emails::table
         .inner_join(user_passwords::table.on(user_emails::user_id.eq(user_passwords::user_id)))
-        .filter(user_emails::email.eq(user_email))
-        .select((
-            user_emails::user_id,
-            user_emails::email,
-            user_passwords::password_hash,
-        ));
-    let result = query.get_result::<UserCredentials>(connection);
+        .filter(user_emails::email_normalized.eq(normalize_email(user_email)))
+        .select((user_emails::user_id, user_passwords::password_hash));
+    let result = query.get_result::<UserCredentialsRecord>(connection);
     match result {
         Ok(user) => Ok(user),
         Err(e) => Err(ERR_DATABASE_RESOURCE_NOT_FOUND.with_internal_error(e.to_string())),
     }
 }
 
+/// registers the user in a single transaction, the unique emails make the concurrent
+/// registrations of the same email safe: the last one fails with `ERR_DATABASE_RECORD_EXISTS`
+///
+/// `user_ids` generates the ids of the attempts (`generate_user_id`)
 pub(crate) fn register_new_user_email_password(
     connection: &mut PgConnection,
     user_email: &str,
     password_hash: &str,
+    mut user_ids: impl FnMut() -> String,
 ) -> Result<String, ErrorDetails> {
-    // check that the email is not already in use
-    let credentials = get_user_credentials(connection, user_email);
-    if credentials.is_ok() {
-        return Err(
+    let email_normalized = normalize_email(user_email);
+    for _ in 0..USER_ID_ATTEMPTS {
+        let new_user_id = user_ids();
+        let registered = connection
+            .transaction::<_, Error, _>(|connection: &mut PgConnection| {
+                // a taken id inserts nothing, the registration is tried again with another id
+                let inserted = diesel::insert_into(users::table)
+                    .values(UserIdentity {
+                        user_id: &new_user_id,
+                    })
+                    .on_conflict_do_nothing()
+                    .execute(connection)?;
+                if inserted == 0 {
+                    return Ok(false);
+                }
+                diesel::insert_into(user_emails::table)
+                    .values(UserEmail {
+                        user_id: &new_user_id,
+                        email: user_email.trim(),
+                        email_normalized: &email_normalized,
+                    })
+                    .execute(connection)?;
+                diesel::insert_into(user_passwords::table)
+                    .values(UserPassword {
+                        user_id: &new_user_id,
+                        password_hash,
+                    })
+                    .execute(connection)?;
+                Ok(true)
+            })
+            .map_err(registration_error)?;
+        if registered {
+            return Ok(new_user_id);
+        }
+    }
+    Err(user_id_exhausted())
+}
+
+/// the registrations failing on a unique email are an email already in use
+pub(crate) fn registration_error(e: Error) -> ErrorDetails {
+    match e {
+        Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => {
             ERR_DATABASE_RECORD_EXISTS.with_internal_error("email already in use".to_string())
-        );
+        }
+        e => ERR_DATABASE_TRANSACTION_FAILED.with_internal_error(e.to_string()),
     }
+}
 
-    // the user is a random 36 character string(case insensitive, alphanumeric)
-    let new_user_id: String = rand::thread_rng()
+/// every generated id of a registration was already used
+pub(crate) fn user_id_exhausted() -> ErrorDetails {
+    ERR_DATABASE_TRANSACTION_FAILED.with_internal_error(format!(
+        "no unused user id after {} attempts",
+        USER_ID_ATTEMPTS
+    ))
+}
+
+/// the canonical form of the email, the users are found by it:
+/// `Alice@X.com ` and `alice@x.com` are the same email
+///
+/// only the spaces are trimmed and only the ASCII letters are lowercased, like the SQL
+/// `trim` and `lower` (without ICU) of the migrations that filled the existing emails
+pub(crate) fn normalize_email(email: &str) -> String {
+    email.trim_matches(' ').to_ascii_lowercase()
+}
+
+/// the user is a random 36 character string(case insensitive, alphanumeric)
+pub(crate) fn generate_user_id() -> String {
+    rand::thread_rng()
         .sample_iter(&rand::distributions::Alphanumeric)
         .take(36)
         .map(char::from)
-        .collect();
-    let transaction_result =
-        connection.transaction::<_, diesel::result::Error, _>(|connection: &mut PgConnection| {
-            diesel::insert_into(users::table)
-                .values(UserIdentity {
-                    user_id: &new_user_id,
-                })
-                .execute(connection)?;
-            diesel::insert_into(user_emails::table)
-                .values(UserEmail {
-                    user_id: &new_user_id,
-                    email: user_email,
-                })
-                .execute(connection)?;
-            diesel::insert_into(user_passwords::table)
-                .values(UserPassword {
-                    user_id: &new_user_id,
-                    password_hash,
-                })
-                .execute(connection)?;
-            Ok(())
-        });
-    match transaction_result {
-        Ok(_) => Ok(new_user_id),
-        Err(e) => Err(ERR_DATABASE_TRANSACTION_FAILED.with_internal_error(e.to_string())),
-    }
+        .collect()
 }
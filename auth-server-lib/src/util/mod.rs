@@ -1,5 +1,14 @@
 pub(crate) mod security;
 pub(crate) mod database{
     pub(crate) mod connection;
+    pub(crate) mod login_application;
+    pub(crate) mod memory;
+    pub(crate) mod migrations;
+    pub(crate) mod pairwise_subject;
+    pub(crate) mod postgres;
+    pub(crate) mod refresh_token;
+    pub(crate) mod revoked_token;
+    #[cfg(feature = "sqlite")]
+    pub(crate) mod sqlite;
     pub(crate) mod user_email;
-}
\ No newline at end of file
+}
\ No newline at end of file
@@ -0,0 +1,28 @@
+use std::sync::{Arc, OnceLock};
+
+use token_helper::dpop::{DpopVerifier, MemoryReplayCache};
+
+use crate::{
+    api::{errors::ErrorDetails, model::DpopRequest},
+    util::security::token_issuer::TokenIssuerConfig,
+};
+
+/// the proofs already used on this instance of the server,
+/// a proof is only accepted for a minute so the cache stays small
+static DPOP_VERIFIER: OnceLock<DpopVerifier> = OnceLock::new();
+
+fn dpop_verifier(config: &TokenIssuerConfig) -> &'static DpopVerifier {
+    DPOP_VERIFIER.get_or_init(|| {
+        DpopVerifier::new(Arc::new(MemoryReplayCache::new())).with_leeway(config.leeway)
+    })
+}
+
+/// verifies the DPoP proof sent with a token request
+/// and returns the thumbprint of its key
+pub(crate) fn verify_dpop_proof(
+    config: &TokenIssuerConfig,
+    request: &DpopRequest,
+) -> Result<String, ErrorDetails> {
+    let proof = dpop_verifier(config).verify(request.proof, request.method, request.uri, None)?;
+    Ok(proof.jkt)
+}
@@ -1 +1,6 @@
-pub(crate) mod password_hasher;
\ No newline at end of file
+pub(crate) mod dpop;
+pub(crate) mod pairwise_subject;
+pub(crate) mod password_hasher;
+pub(crate) mod refresh_token;
+pub(crate) mod revocation;
+pub(crate) mod token_issuer;
@@ -0,0 +1,28 @@
+use token_helper::{pairwise::SubjectResolver, user::UserData};
+
+use crate::api::repository::UserRepository;
+
+/// maps the pairwise subjects recorded by the repository back to the users
+///
+/// it is only used for the audiences of the applications with pairwise subjects,
+/// a subject that is not found is rejected (for example when the user was deleted)
+pub(crate) struct PairwiseSubjectStore<R> {
+    repository: R,
+}
+
+impl<R: UserRepository> PairwiseSubjectStore<R> {
+    pub fn new(repository: R) -> Self {
+        Self { repository }
+    }
+}
+
+impl<R: UserRepository + Send + Sync> SubjectResolver for PairwiseSubjectStore<R> {
+    fn resolve(&self, user_data: &UserData, audience: &str) -> Result<Option<UserData>, String> {
+        let user_id = self
+            .repository
+            .get_pairwise_subject_user_id(&user_data.user_id, audience)
+            .map_err(|e| e.internal_error.unwrap_or_default())?
+            .ok_or_else(|| format!("unknown pairwise subject {}", user_data.user_id))?;
+        UserData::new(user_id, user_data.realm.clone()).map(Some)
+    }
+}
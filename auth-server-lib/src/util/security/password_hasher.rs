@@ -1,4 +1,5 @@
 pub(crate) mod argon2;
+pub(crate) mod pool;
 
 pub(crate) trait PasswordHasher {
     /// Hashes the password and creates a salt for it
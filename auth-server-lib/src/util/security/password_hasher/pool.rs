@@ -0,0 +1,137 @@
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::{mpsc, Arc, Mutex, OnceLock},
+    thread,
+};
+
+use dboilerplate::util::configuration;
+use tokio::sync::oneshot;
+
+use crate::{api::errors::*, util::security::password_hasher::PasswordHasher};
+
+/// the configuration key of the number of hashing threads
+const CONCURRENCY_KEY: &str = "PASSWORD_HASHING_CONCURRENCY";
+/// the configuration key of the number of jobs waiting for a hashing thread
+const QUEUE_SIZE_KEY: &str = "PASSWORD_HASHING_QUEUE_SIZE";
+const DEFAULT_QUEUE_SIZE: usize = 256;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// the threads hashing the passwords and the client secrets
+///
+/// argon2 is slow on purpose, so the hashes do not run on the async workers
+/// and at most `concurrency` hashes run at the same time, at most `queue_size`
+/// other requests wait for a free thread and the next ones fail right away
+pub(crate) struct HashingPool {
+    sender: mpsc::SyncSender<Job>,
+}
+
+impl HashingPool {
+    pub fn new(concurrency: usize, queue_size: usize) -> Result<Self, String> {
+        let (sender, receiver) = mpsc::sync_channel::<Job>(queue_size);
+        let receiver = Arc::new(Mutex::new(receiver));
+        for i in 0..concurrency {
+            let receiver = receiver.clone();
+            thread::Builder::new()
+                .name(format!("password-hashing-{}", i))
+                .spawn(move || loop {
+                    // the lock is released before the job runs
+                    let job = match receiver.lock().unwrap_or_else(|e| e.into_inner()).recv() {
+                        Ok(job) => job,
+                        // the pool was dropped
+                        Err(_) => break,
+                    };
+                    // a panic only fails its own request (its result is never sent)
+                    let _ = panic::catch_unwind(AssertUnwindSafe(job));
+                })
+                .map_err(|e| format!("could not start the password hashing threads: {}", e))?;
+        }
+        Ok(Self { sender })
+    }
+
+    /// runs the job on a hashing thread and waits for its result,
+    /// fails if the queue of the pool is full
+    pub async fn run<T, F>(&self, job: F) -> Result<T, ErrorDetails>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let (result_sender, result_receiver) = oneshot::channel();
+        self.sender
+            .try_send(Box::new(move || {
+                let _ = result_sender.send(job());
+            }))
+            .map_err(|e| match e {
+                mpsc::TrySendError::Full(_) => ERR_HASHING_QUEUE_FULL
+                    .with_internal_error("the password hashing queue is full".to_string()),
+                mpsc::TrySendError::Disconnected(_) => ERR_UNKNOWN_INTERNAL_ERROR
+                    .with_internal_error("the password hashing threads stopped".to_string()),
+            })?;
+        result_receiver
+            .await
+            .map_err(|e| ERR_UNKNOWN_INTERNAL_ERROR.with_internal_error(e.to_string()))
+    }
+
+    /// hashes the password and creates a salt for it
+    pub async fn hash_password<H: PasswordHasher>(
+        &self,
+        password: &str,
+    ) -> Result<String, ErrorDetails> {
+        let password = password.to_string();
+        self.run(move || H::hash_password(password.as_bytes()))
+            .await?
+            .map_err(|e| ERR_UNKNOWN_INTERNAL_ERROR.with_internal_error(e))
+    }
+
+    /// verifies the password against the encoded hash,
+    /// the inner error is a password that does not match
+    pub async fn verify_password<H: PasswordHasher>(
+        &self,
+        password: &str,
+        encoded: String,
+    ) -> Result<Result<(), String>, ErrorDetails> {
+        let password = password.to_string();
+        self.run(move || H::verify_password(password.as_bytes(), encoded.as_bytes()))
+            .await
+    }
+}
+
+/// the pool started by `get_hashing_pool`
+static HASHING_POOL: OnceLock<HashingPool> = OnceLock::new();
+
+/// returns the pool of the hashing threads, it is started once
+/// (at startup by the server, see `load_configuration`)
+///
+/// `PASSWORD_HASHING_CONCURRENCY` is the number of threads, the number of cpus by default,
+/// and `PASSWORD_HASHING_QUEUE_SIZE` the number of waiting requests, 256 by default
+pub(crate) fn get_hashing_pool() -> Result<&'static HashingPool, ErrorDetails> {
+    if let Some(pool) = HASHING_POOL.get() {
+        return Ok(pool);
+    }
+    let config = configuration::get_config(None, None);
+    let concurrency = match config.find_value(CONCURRENCY_KEY) {
+        Ok(_) => config
+            .extract_inner::<usize>(CONCURRENCY_KEY)
+            .map_err(|e| ERR_HASHING_CONFIGURATION_INVALID.with_internal_error(e.to_string()))?,
+        Err(_) => thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1),
+    };
+    if concurrency == 0 {
+        return Err(ERR_HASHING_CONFIGURATION_INVALID
+            .with_internal_error(format!("{} must be at least 1", CONCURRENCY_KEY)));
+    }
+    let queue_size = match config.find_value(QUEUE_SIZE_KEY) {
+        Ok(_) => config
+            .extract_inner::<usize>(QUEUE_SIZE_KEY)
+            .map_err(|e| ERR_HASHING_CONFIGURATION_INVALID.with_internal_error(e.to_string()))?,
+        Err(_) => DEFAULT_QUEUE_SIZE,
+    };
+    if queue_size == 0 {
+        return Err(ERR_HASHING_CONFIGURATION_INVALID
+            .with_internal_error(format!("{} must be at least 1", QUEUE_SIZE_KEY)));
+    }
+    let pool = HashingPool::new(concurrency, queue_size)
+        .map_err(|e| ERR_HASHING_CONFIGURATION_INVALID.with_internal_error(e))?;
+    Ok(HASHING_POOL.get_or_init(|| pool))
+}
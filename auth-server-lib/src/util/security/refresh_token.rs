@@ -0,0 +1,22 @@
+use rand::Rng;
+use sha2::{Digest, Sha256};
+
+/// length of the opaque refresh tokens
+const REFRESH_TOKEN_LENGTH: usize = 64;
+
+/// generates a new opaque refresh token (random alphanumeric string)
+pub(crate) fn generate_refresh_token() -> String {
+    rand::thread_rng()
+        .sample_iter(&rand::distributions::Alphanumeric)
+        .take(REFRESH_TOKEN_LENGTH)
+        .map(char::from)
+        .collect()
+}
+
+/// hashes a refresh token (sha-256 in hex) so only the hash is stored
+pub(crate) fn hash_refresh_token(refresh_token: &str) -> String {
+    Sha256::digest(refresh_token.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
@@ -0,0 +1,75 @@
+use std::{
+    collections::HashMap,
+    sync::{Mutex, OnceLock},
+    time::SystemTime,
+};
+
+use token_helper::token_helper::RevocationCheck;
+
+use crate::api::{errors::ErrorDetails, repository::TokenRepository};
+
+/// revoked token ids known by this process and their expiration time
+/// only the revoked tokens are cached, so a token revoked by another
+/// instance of the server is always found in the database
+static REVOKED_TOKENS: OnceLock<Mutex<HashMap<String, SystemTime>>> = OnceLock::new();
+
+fn revoked_tokens_cache() -> &'static Mutex<HashMap<String, SystemTime>> {
+    REVOKED_TOKENS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// adds a revoked token to the cache and forgets the expired ones
+pub(crate) fn cache_revoked_token(jwt_id: &str, expires_at: SystemTime) {
+    let mut cache = revoked_tokens_cache()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    let now = SystemTime::now();
+    cache.retain(|_, expires_at| *expires_at > now);
+    if expires_at > now {
+        cache.insert(jwt_id.to_string(), expires_at);
+    }
+}
+
+/// checks if the token is in the cache of revoked tokens
+pub(crate) fn is_cached_revoked_token(jwt_id: &str) -> bool {
+    let cache = revoked_tokens_cache()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    cache.contains_key(jwt_id)
+}
+
+/// revocation store backed by the revoked tokens of the repository and the in-process cache
+pub(crate) struct RevocationStore<'r, R> {
+    repository: &'r R,
+}
+
+impl<'r, R: TokenRepository> RevocationStore<'r, R> {
+    pub fn new(repository: &'r R) -> Self {
+        Self { repository }
+    }
+
+    /// revokes the token until it expires
+    pub fn revoke(&self, jwt_id: &str, expires_at: SystemTime) -> Result<(), ErrorDetails> {
+        self.repository.insert_revoked_token(jwt_id, expires_at)?;
+        cache_revoked_token(jwt_id, expires_at);
+        Ok(())
+    }
+}
+
+impl<R: TokenRepository> RevocationCheck for RevocationStore<'_, R> {
+    fn is_revoked(&self, jwt_id: &str) -> Result<bool, String> {
+        if is_cached_revoked_token(jwt_id) {
+            return Ok(true);
+        }
+        let revoked = self
+            .repository
+            .get_revoked_token(jwt_id)
+            .map_err(|e| e.internal_error.unwrap_or_default())?;
+        match revoked {
+            Some(expires_at) => {
+                cache_revoked_token(jwt_id, expires_at);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
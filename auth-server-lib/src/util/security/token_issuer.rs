@@ -0,0 +1,421 @@
+use std::{
+    sync::OnceLock,
+    time::{Duration, SystemTime},
+};
+
+use crate::api::errors::*;
+use dboilerplate::util::configuration;
+use serde::{de::DeserializeOwned, Deserialize};
+use token_helper::{
+    claims::{Actor, Confirmation, CustomClaims, TokenClaims},
+    format::TokenFormat,
+    key_provider::{EncryptedPem, EnvKey, KeyProvider, PemFile, SecretFileKey},
+    key_ring::{KeyPurpose, KeyRing, KeyStatus},
+    manager::TokenOptions,
+    pairwise::pairwise_user_data,
+    token_helper::{ContentEncryption, EncryptionPolicy, KeyManagementAlgorithm, SigningAlgorithm},
+    user::UserData,
+    validation::ValidationOptions,
+};
+
+#[cfg(feature = "paseto")]
+use token_helper::paseto::{PasetoLocalKey, PasetoPublicKey};
+
+/// default lifetime of an access token (in seconds)
+const DEFAULT_TOKEN_LIFETIME: u64 = 3600;
+/// default lifetime of a refresh token (in seconds, 30 days)
+const DEFAULT_REFRESH_TOKEN_LIFETIME: u64 = 2_592_000;
+/// default clock skew tolerated between the nodes (in seconds)
+const DEFAULT_TOKEN_LEEWAY: u64 = 0;
+/// default realm of the users registered by this server
+const DEFAULT_TOKEN_REALM: &str = "local";
+/// default format of the access tokens
+const DEFAULT_TOKEN_FORMAT: &str = "jwt";
+
+/// settings used to issue the access tokens
+pub(crate) struct TokenIssuerConfig {
+    pub issuer: String,
+    pub realm: String,
+    pub format: TokenFormat,
+    pub lifetime: Duration,
+    pub refresh_lifetime: Duration,
+    // clock skew tolerated when validating the tokens
+    pub leeway: Duration,
+    // secret used to derive the subjects of the applications with pairwise subjects
+    pub pairwise_secret: Option<Vec<u8>>,
+}
+
+/// the settings resolved at startup, see `load_token_issuer_config`
+static TOKEN_ISSUER_CONFIG: OnceLock<TokenIssuerConfig> = OnceLock::new();
+
+/// where a key is read from
+///
+/// `private_key_path` is a PEM file, `private_key_env` an environment variable with the key
+/// (or the path of a secret file in `<private_key_env>_FILE`),
+/// `passphrase_env` names the variable (or `<passphrase_env>_FILE`) with the passphrase
+/// of an encrypted PKCS#8 key
+#[derive(Deserialize)]
+struct KeySourceConfig {
+    private_key_path: Option<String>,
+    private_key_env: Option<String>,
+    passphrase_env: Option<String>,
+}
+
+impl KeySourceConfig {
+    /// reads the `<name>_PATH`, `<name>_ENV` and `<name>_PASSPHRASE_ENV` settings
+    fn from_config(name: &str) -> Result<Self, ErrorDetails> {
+        Ok(Self {
+            private_key_path: read_setting(&format!("{}_PATH", name))?,
+            private_key_env: read_setting(&format!("{}_ENV", name))?,
+            passphrase_env: read_setting(&format!("{}_PASSPHRASE_ENV", name))?,
+        })
+    }
+
+    fn is_set(&self) -> bool {
+        self.private_key_path.is_some() || self.private_key_env.is_some()
+    }
+
+    fn provider(&self) -> Result<Box<dyn KeyProvider>, String> {
+        let source = match (&self.private_key_path, &self.private_key_env) {
+            (Some(path), None) => Box::new(PemFile::new(path)),
+            (None, Some(name)) => env_or_secret_file(name),
+            _ => return Err("set either the key path or the key variable".to_string()),
+        };
+        match &self.passphrase_env {
+            Some(name) => {
+                let passphrase = env_or_secret_file(name)
+                    .load_key()
+                    .map_err(|e| e.to_string())?;
+                Ok(Box::new(EncryptedPem::new(
+                    source,
+                    trim_line_end(passphrase),
+                )))
+            }
+            None => Ok(source),
+        }
+    }
+
+    /// loads the key, the error names the key so the configuration can be fixed
+    fn load_key(&self, key_name: &str) -> Result<Vec<u8>, ErrorDetails> {
+        self.provider()
+            .and_then(|provider| provider.load_key().map_err(|e| e.to_string()))
+            .map_err(|e| {
+                ERR_TOKEN_CONFIGURATION_NOT_FOUND
+                    .with_internal_error(format!("{}: {}", key_name, e))
+            })
+    }
+}
+
+/// reads an optional setting, `None` when it is not set
+/// but an error when its value is malformed
+fn read_setting<T: DeserializeOwned>(key: &str) -> Result<Option<T>, ErrorDetails> {
+    let config = configuration::get_config(None, None);
+    match config.find_value(key) {
+        Ok(_) => config.extract_inner(key).map(Some).map_err(|e| {
+            ERR_TOKEN_CONFIGURATION_NOT_FOUND.with_internal_error(format!("{}: {}", key, e))
+        }),
+        Err(_) => Ok(None),
+    }
+}
+
+/// the variable itself, or the secret file mounted at the path of `<name>_FILE`
+fn env_or_secret_file(name: &str) -> Box<dyn KeyProvider> {
+    match std::env::var_os(name) {
+        Some(_) => Box::new(EnvKey::new(name)),
+        None => Box::new(SecretFileKey::new(name)),
+    }
+}
+
+/// the secret files usually end with a new line
+fn trim_line_end(mut value: Vec<u8>) -> Vec<u8> {
+    while matches!(value.last(), Some(b'\n' | b'\r')) {
+        value.pop();
+    }
+    value
+}
+
+/// a key of the `TOKEN_KEYS` list
+///
+/// ```toml
+/// [[TOKEN_KEYS]]
+/// kid = "sig-2022-10"
+/// algorithm = "EdDSA"
+/// private_key_path = "/devel/certs/token-sig-2022-10.pem"
+///
+/// # optional, the signed tokens are then encrypted (nested tokens)
+/// [[TOKEN_KEYS]]
+/// kid = "enc-2022-11"
+/// algorithm = "ECDH-ES"
+/// private_key_env = "TOKEN_KEY_2022_11"
+/// passphrase_env = "TOKEN_KEY_PASSPHRASE"
+/// ```
+#[derive(Deserialize)]
+struct TokenKeyConfig {
+    kid: String,
+    // ECDH-ES, ECDH-ES+A256KW or RSA-OAEP-256 for encryption keys,
+    // EdDSA or ES256 for signing keys
+    algorithm: String,
+    #[serde(flatten)]
+    source: KeySourceConfig,
+    // retiring keys only validate the tokens issued before the rotation
+    #[serde(default)]
+    retiring: bool,
+}
+
+/// loads the token keys from the `TOKEN_KEYS` list of the configuration,
+/// an active signing key is required as the tokens are always signed
+///
+/// `TOKEN_CONTENT_ENCRYPTION` sets the content encryption of the issued tokens
+/// (A128CBC-HS256 or A256GCM, A128CBC-HS256 by default),
+/// `TOKEN_ACCEPTED_ALGORITHMS` and `TOKEN_ACCEPTED_ENCRYPTIONS` restrict the algorithms
+/// accepted when the tokens are decrypted (by default the ones of the configured keys)
+fn get_token_key_ring() -> Result<KeyRing, ErrorDetails> {
+    let config = configuration::get_config(None, None);
+    let keys: Vec<TokenKeyConfig> = config
+        .extract_inner("TOKEN_KEYS")
+        .map_err(|e| ERR_TOKEN_CONFIGURATION_NOT_FOUND.with_internal_error(e.to_string()))?;
+    let mut key_ring = KeyRing::new();
+    for key in keys {
+        let purpose = match key.algorithm.as_str() {
+            "EdDSA" => KeyPurpose::Signing(SigningAlgorithm::EdDSA),
+            "ES256" => KeyPurpose::Signing(SigningAlgorithm::ES256),
+            other => KeyPurpose::Encryption(key_management(other)?),
+        };
+        let status = match key.retiring {
+            true => KeyStatus::Retiring,
+            false => KeyStatus::Active,
+        };
+        let private_key = key.source.load_key(&format!("token key {}", key.kid))?;
+        key_ring
+            .add_key(&key.kid, purpose, private_key, status)
+            .map_err(|e| ERR_TOKEN_CONFIGURATION_NOT_FOUND.with_internal_error(e.to_string()))?;
+    }
+    if key_ring.active_signing_key().is_none() {
+        return Err(ERR_TOKEN_CONFIGURATION_NOT_FOUND
+            .with_internal_error("TOKEN_KEYS has no active signing key".to_string()));
+    }
+    if let Some(name) = read_setting::<String>("TOKEN_CONTENT_ENCRYPTION")? {
+        key_ring.set_content_encryption(content_encryption(&name)?);
+    }
+    let accepted_algorithms: Option<Vec<String>> = read_setting("TOKEN_ACCEPTED_ALGORITHMS")?;
+    let accepted_encryptions: Option<Vec<String>> = read_setting("TOKEN_ACCEPTED_ENCRYPTIONS")?;
+    if accepted_algorithms.is_some() || accepted_encryptions.is_some() {
+        let key_managements = match accepted_algorithms {
+            Some(names) => names
+                .iter()
+                .map(|name| key_management(name))
+                .collect::<Result<Vec<_>, _>>()?,
+            None => key_ring
+                .keys()
+                .iter()
+                .filter_map(|k| match k.purpose() {
+                    KeyPurpose::Encryption(algorithm) => Some(algorithm),
+                    KeyPurpose::Signing(_) => None,
+                })
+                .collect(),
+        };
+        let content_encryptions = match accepted_encryptions {
+            Some(names) => names
+                .iter()
+                .map(|name| content_encryption(name))
+                .collect::<Result<Vec<_>, _>>()?,
+            None => vec![key_ring.content_encryption()],
+        };
+        key_ring.set_encryption_policy(EncryptionPolicy::new(
+            &key_managements,
+            &content_encryptions,
+        ));
+    }
+    Ok(key_ring)
+}
+
+/// loads the keys of the token format selected by `TOKEN_FORMAT`
+///
+/// `jwt` (default) issues signed (or signed and encrypted) json web tokens with the `TOKEN_KEYS`,
+/// `v4.local` and `v4.public` issue PASETO tokens with the key of `PASETO_KEY_PATH`
+/// or `PASETO_KEY_ENV` (a `k4.local` PASERK or an Ed25519 private key in PEM format),
+/// the PASETO formats require the `paseto` feature
+fn get_token_format() -> Result<TokenFormat, ErrorDetails> {
+    let format: String =
+        read_setting("TOKEN_FORMAT")?.unwrap_or_else(|| DEFAULT_TOKEN_FORMAT.to_string());
+    match format.as_str() {
+        "jwt" => Ok(TokenFormat::Jwt(get_token_key_ring()?)),
+        #[cfg(feature = "paseto")]
+        "v4.local" => {
+            let paserk = String::from_utf8(read_paseto_key()?).map_err(|e| {
+                ERR_TOKEN_CONFIGURATION_NOT_FOUND.with_internal_error(e.to_string())
+            })?;
+            let key = PasetoLocalKey::from_paserk(&paserk).map_err(|e| {
+                ERR_TOKEN_CONFIGURATION_NOT_FOUND.with_internal_error(e.to_string())
+            })?;
+            Ok(TokenFormat::PasetoLocal(key))
+        }
+        #[cfg(feature = "paseto")]
+        "v4.public" => {
+            let key = PasetoPublicKey::from_pem(&read_paseto_key()?).map_err(|e| {
+                ERR_TOKEN_CONFIGURATION_NOT_FOUND.with_internal_error(e.to_string())
+            })?;
+            Ok(TokenFormat::PasetoPublic(key))
+        }
+        other => Err(ERR_TOKEN_CONFIGURATION_NOT_FOUND
+            .with_internal_error(format!("unsupported token format {}", other))),
+    }
+}
+
+#[cfg(feature = "paseto")]
+fn read_paseto_key() -> Result<Vec<u8>, ErrorDetails> {
+    KeySourceConfig::from_config("PASETO_KEY")?.load_key("PASETO_KEY")
+}
+
+fn key_management(name: &str) -> Result<KeyManagementAlgorithm, ErrorDetails> {
+    KeyManagementAlgorithm::from_name(name).ok_or_else(|| {
+        ERR_TOKEN_CONFIGURATION_NOT_FOUND
+            .with_internal_error(format!("unsupported key algorithm {}", name))
+    })
+}
+
+fn content_encryption(name: &str) -> Result<ContentEncryption, ErrorDetails> {
+    ContentEncryption::from_name(name).ok_or_else(|| {
+        ERR_TOKEN_CONFIGURATION_NOT_FOUND
+            .with_internal_error(format!("unsupported content encryption {}", name))
+    })
+}
+
+/// loads the token issuer settings from the configuration
+///
+/// `TOKEN_ISSUER` and the keys of the `TOKEN_FORMAT` are required,
+/// `TOKEN_REALM`, `TOKEN_LIFETIME`, `REFRESH_TOKEN_LIFETIME` and `TOKEN_LEEWAY` (seconds) are optional,
+/// `PAIRWISE_SUBJECT_KEY_PATH` or `PAIRWISE_SUBJECT_KEY_ENV` (a secret of at least 32 bytes)
+/// is required by the applications with pairwise subjects,
+/// a malformed optional setting is an error rather than replaced by its default
+pub(crate) fn load_token_issuer_config() -> Result<TokenIssuerConfig, ErrorDetails> {
+    let config = configuration::get_config(None, None);
+    let issuer: String = config
+        .extract_inner("TOKEN_ISSUER")
+        .map_err(|e| ERR_TOKEN_CONFIGURATION_NOT_FOUND.with_internal_error(e.to_string()))?;
+    let format = get_token_format()?;
+    let realm: String =
+        read_setting("TOKEN_REALM")?.unwrap_or_else(|| DEFAULT_TOKEN_REALM.to_string());
+    let lifetime: u64 = read_setting("TOKEN_LIFETIME")?.unwrap_or(DEFAULT_TOKEN_LIFETIME);
+    let refresh_lifetime: u64 =
+        read_setting("REFRESH_TOKEN_LIFETIME")?.unwrap_or(DEFAULT_REFRESH_TOKEN_LIFETIME);
+    let leeway: u64 = read_setting("TOKEN_LEEWAY")?.unwrap_or(DEFAULT_TOKEN_LEEWAY);
+    let pairwise_source = KeySourceConfig::from_config("PAIRWISE_SUBJECT_KEY")?;
+    let pairwise_secret = match pairwise_source.is_set() {
+        true => Some(pairwise_source.load_key("PAIRWISE_SUBJECT_KEY")?),
+        false => None,
+    };
+    Ok(TokenIssuerConfig {
+        issuer,
+        realm,
+        format,
+        lifetime: Duration::from_secs(lifetime),
+        refresh_lifetime: Duration::from_secs(refresh_lifetime),
+        leeway: Duration::from_secs(leeway),
+        pairwise_secret,
+    })
+}
+
+/// returns the token issuer settings, they are loaded once
+/// (at startup by the server, see `load_token_issuer_config`)
+pub(crate) fn get_token_issuer_config() -> Result<&'static TokenIssuerConfig, ErrorDetails> {
+    if let Some(config) = TOKEN_ISSUER_CONFIG.get() {
+        return Ok(config);
+    }
+    let config = load_token_issuer_config()?;
+    Ok(TOKEN_ISSUER_CONFIG.get_or_init(|| config))
+}
+
+/// uses the settings of the tests instead of loading the configuration
+#[cfg(test)]
+pub(crate) fn init_token_issuer_config(config: impl FnOnce() -> TokenIssuerConfig) {
+    TOKEN_ISSUER_CONFIG.get_or_init(config);
+}
+
+/// returns the pairwise user id of the user for the audience
+pub(crate) fn pairwise_subject_id(
+    config: &TokenIssuerConfig,
+    user_id: &str,
+    audience: &str,
+) -> Result<String, ErrorDetails> {
+    let secret = config.pairwise_secret.as_ref().ok_or_else(|| {
+        ERR_TOKEN_CONFIGURATION_NOT_FOUND
+            .with_internal_error("PAIRWISE_SUBJECT_KEY_PATH is not set".to_string())
+    })?;
+    let user_data = UserData::new(user_id.to_string(), config.realm.clone())
+        .map_err(|e| ERR_TOKEN_CREATION_FAILED.with_internal_error(e))?;
+    let pairwise = pairwise_user_data(secret, &user_data, audience)
+        .map_err(|e| ERR_TOKEN_CONFIGURATION_NOT_FOUND.with_internal_error(e.to_string()))?;
+    Ok(pairwise.user_id)
+}
+
+/// issues an access token for the user targeting the given audience
+/// `auth_time` is the time of the login, the users can only log in with a password,
+/// the token is bound to the DPoP key or the client certificate of the confirmation (`cnf`)
+pub(crate) fn issue_access_token(
+    config: &TokenIssuerConfig,
+    user_id: &str,
+    audience: &str,
+    auth_time: SystemTime,
+    confirmation: Confirmation,
+) -> Result<String, ErrorDetails> {
+    let user_data = UserData::new(user_id.to_string(), config.realm.clone())
+        .map_err(|e| ERR_TOKEN_CREATION_FAILED.with_internal_error(e))?;
+    let mut claims = CustomClaims::new();
+    claims.auth_time = Some(auth_time);
+    claims.amr = vec!["pwd".to_string()];
+    claims.cnf = confirmation;
+    let options = TokenOptions::new(&config.issuer, vec![audience.to_string()])
+        .with_claims(claims)
+        .with_lifetime(config.lifetime);
+    config
+        .format
+        .encode_user_data(&user_data, &options)
+        .map_err(ErrorDetails::from)
+}
+
+/// issues the token of a token exchange (RFC 8693) for the user of the subject token,
+/// the authentication claims of the subject token are kept and the actor is recorded
+/// in the `act` claim, the token does not outlive the subject token
+pub(crate) fn issue_exchanged_token(
+    config: &TokenIssuerConfig,
+    user_id: &str,
+    audience: &str,
+    subject: &TokenClaims,
+    actor: &str,
+    scopes: Vec<String>,
+) -> Result<(String, Duration), ErrorDetails> {
+    let user_data = UserData::new(user_id.to_string(), config.realm.clone())
+        .map_err(|e| ERR_TOKEN_CREATION_FAILED.with_internal_error(e))?;
+    let lifetime = subject
+        .expires_at
+        .duration_since(SystemTime::now())
+        .map_err(|_| {
+            ERR_TOKEN_EXPIRED.with_internal_error("the subject token expired".to_string())
+        })?
+        .min(config.lifetime);
+    let mut claims = CustomClaims::new();
+    claims.auth_time = subject.custom.auth_time;
+    claims.amr = subject.custom.amr.clone();
+    claims.scopes = scopes;
+    claims.act = Some(match subject.custom.act.clone() {
+        Some(previous) => previous.delegate_to(actor),
+        None => Actor::new(actor),
+    });
+    let options = TokenOptions::new(&config.issuer, vec![audience.to_string()])
+        .with_claims(claims)
+        .with_lifetime(lifetime);
+    let token = config
+        .format
+        .encode_user_data(&user_data, &options)
+        .map_err(ErrorDetails::from)?;
+    Ok((token, lifetime))
+}
+
+/// the rules used to validate the access tokens issued for the audience
+pub(crate) fn validation_options(config: &TokenIssuerConfig, audience: &str) -> ValidationOptions {
+    ValidationOptions::new(&config.issuer, &[audience])
+        .with_leeway(config.leeway)
+        .with_realm(&config.realm)
+        .require_claim("jti")
+}
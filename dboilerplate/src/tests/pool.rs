@@ -0,0 +1,60 @@
+use std::time::Duration;
+
+use diesel::PgConnection;
+use figment::{
+    providers::{Format, Toml},
+    Figment,
+};
+
+use crate::util::pool::{create_pool, PoolConfig};
+
+#[test]
+fn test_default_pool_config() {
+    let pool_config = PoolConfig::from_config(&Figment::new()).unwrap();
+    assert_eq!(pool_config, PoolConfig::default());
+}
+
+#[test]
+fn test_pool_config() {
+    let config = Figment::from(Toml::string(
+        r#"
+        DATABASE_POOL_MAX_SIZE = 20
+        DATABASE_POOL_MIN_IDLE = 0
+        DATABASE_POOL_CONNECTION_TIMEOUT = 2
+        DATABASE_POOL_IDLE_TIMEOUT = 0
+        DATABASE_POOL_HEALTH_CHECK = false
+        "#,
+    ));
+    let pool_config = PoolConfig::from_config(&config).unwrap();
+    assert_eq!(pool_config.max_size, 20);
+    assert_eq!(pool_config.min_idle, 0);
+    assert_eq!(pool_config.connection_timeout, Duration::from_secs(2));
+    assert_eq!(pool_config.idle_timeout, None);
+    assert_eq!(pool_config.max_lifetime, Some(Duration::from_secs(1800)));
+    assert!(!pool_config.health_check);
+}
+
+#[test]
+fn test_invalid_pool_config() {
+    for invalid in [
+        "DATABASE_POOL_MAX_SIZE = 0",
+        "DATABASE_POOL_MAX_SIZE = \"ten\"",
+        "DATABASE_POOL_MIN_IDLE = 11",
+        "DATABASE_POOL_CONNECTION_TIMEOUT = 0",
+    ] {
+        let config = Figment::from(Toml::string(invalid));
+        assert!(PoolConfig::from_config(&config).is_err(), "{}", invalid);
+    }
+}
+
+#[test]
+fn test_pool_timeout() {
+    let pool_config = PoolConfig {
+        min_idle: 0,
+        connection_timeout: Duration::from_secs(1),
+        ..PoolConfig::default()
+    };
+    // the pool is created even if the database can not be reached
+    let pool = create_pool::<PgConnection>("postgres://localhost:9/offline", &pool_config);
+    assert!(pool.get().is_err());
+}
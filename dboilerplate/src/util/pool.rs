@@ -0,0 +1,137 @@
+use std::time::Duration;
+
+use diesel::r2d2::{Builder, ConnectionManager, CustomizeConnection, Error, Pool, R2D2Connection};
+use figment::Figment;
+use serde::Deserialize;
+
+/// settings of the database connection pool, read from the `DATABASE_POOL_*` keys
+/// of the configuration, the missing keys keep their default value
+///
+/// | key                                | default | |
+/// |------------------------------------|---------|-|
+/// | `DATABASE_POOL_MAX_SIZE`           | 10      | maximum number of connections |
+/// | `DATABASE_POOL_MIN_IDLE`           | 1       | idle connections kept open |
+/// | `DATABASE_POOL_CONNECTION_TIMEOUT` | 5       | seconds to wait for a free connection |
+/// | `DATABASE_POOL_IDLE_TIMEOUT`       | 600     | seconds before an idle connection is closed (0 never) |
+/// | `DATABASE_POOL_MAX_LIFETIME`       | 1800    | seconds before a connection is replaced (0 never) |
+/// | `DATABASE_POOL_HEALTH_CHECK`       | true    | checks the connections before using them |
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoolConfig {
+    pub max_size: u32,
+    pub min_idle: u32,
+    pub connection_timeout: Duration,
+    pub idle_timeout: Option<Duration>,
+    pub max_lifetime: Option<Duration>,
+    pub health_check: bool,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self {
+            max_size: 10,
+            min_idle: 1,
+            connection_timeout: Duration::from_secs(5),
+            idle_timeout: Some(Duration::from_secs(600)),
+            max_lifetime: Some(Duration::from_secs(1800)),
+            health_check: true,
+        }
+    }
+}
+
+impl PoolConfig {
+    /// reads the pool settings of the configuration
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dboilerplate::util::pool::PoolConfig;
+    /// use figment::{providers::{Format, Toml}, Figment};
+    ///
+    /// let config = Figment::from(Toml::string("DATABASE_POOL_MAX_SIZE = 4"));
+    /// let pool_config = PoolConfig::from_config(&config).unwrap();
+    /// assert_eq!(pool_config.max_size, 4);
+    /// assert!(pool_config.health_check);
+    /// ```
+    pub fn from_config(config: &Figment) -> Result<Self, String> {
+        let default = PoolConfig::default();
+        let pool_config = PoolConfig {
+            max_size: read(config, "DATABASE_POOL_MAX_SIZE", default.max_size)?,
+            min_idle: read(config, "DATABASE_POOL_MIN_IDLE", default.min_idle)?,
+            connection_timeout: Duration::from_secs(read(
+                config,
+                "DATABASE_POOL_CONNECTION_TIMEOUT",
+                default.connection_timeout.as_secs(),
+            )?),
+            idle_timeout: read_timeout(config, "DATABASE_POOL_IDLE_TIMEOUT", default.idle_timeout)?,
+            max_lifetime: read_timeout(config, "DATABASE_POOL_MAX_LIFETIME", default.max_lifetime)?,
+            health_check: read(config, "DATABASE_POOL_HEALTH_CHECK", default.health_check)?,
+        };
+        if pool_config.max_size == 0 {
+            return Err("DATABASE_POOL_MAX_SIZE must be at least 1".to_string());
+        }
+        if pool_config.min_idle > pool_config.max_size {
+            return Err(
+                "DATABASE_POOL_MIN_IDLE can not be over DATABASE_POOL_MAX_SIZE".to_string(),
+            );
+        }
+        if pool_config.connection_timeout.is_zero() {
+            return Err("DATABASE_POOL_CONNECTION_TIMEOUT must be at least 1".to_string());
+        }
+        Ok(pool_config)
+    }
+}
+
+/// creates a pool of connections to the database, the connections are opened
+/// in the background so the pool can be created while the database is offline
+pub fn create_pool<C: R2D2Connection + 'static>(
+    database_url: &str,
+    pool_config: &PoolConfig,
+) -> Pool<ConnectionManager<C>> {
+    pool_builder(pool_config).build_unchecked(ConnectionManager::new(database_url))
+}
+
+/// creates a pool like `create_pool`, the customizer prepares every new connection
+/// (for example with the pragmas of a SQLite connection)
+pub fn create_pool_with_customizer<C: R2D2Connection + 'static>(
+    database_url: &str,
+    pool_config: &PoolConfig,
+    customizer: Box<dyn CustomizeConnection<C, Error>>,
+) -> Pool<ConnectionManager<C>> {
+    pool_builder(pool_config)
+        .connection_customizer(customizer)
+        .build_unchecked(ConnectionManager::new(database_url))
+}
+
+fn pool_builder<C: R2D2Connection + 'static>(
+    pool_config: &PoolConfig,
+) -> Builder<ConnectionManager<C>> {
+    Pool::builder()
+        .max_size(pool_config.max_size)
+        .min_idle(Some(pool_config.min_idle))
+        .connection_timeout(pool_config.connection_timeout)
+        .idle_timeout(pool_config.idle_timeout)
+        .max_lifetime(pool_config.max_lifetime)
+        .test_on_check_out(pool_config.health_check)
+}
+
+// returns the default value when the key is not set
+fn read<'a, T: Deserialize<'a>>(config: &Figment, key: &str, default: T) -> Result<T, String> {
+    match config.find_value(key) {
+        Ok(_) => config.extract_inner(key).map_err(|e| e.to_string()),
+        Err(_) => Ok(default),
+    }
+}
+
+// a timeout of 0 seconds disables it
+fn read_timeout(
+    config: &Figment,
+    key: &str,
+    default: Option<Duration>,
+) -> Result<Option<Duration>, String> {
+    let default = default.map(|timeout| timeout.as_secs()).unwrap_or(0);
+    let seconds: u64 = read(config, key, default)?;
+    Ok(match seconds {
+        0 => None,
+        seconds => Some(Duration::from_secs(seconds)),
+    })
+}
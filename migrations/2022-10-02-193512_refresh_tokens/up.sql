@@ -0,0 +1,20 @@
+-- Your SQL goes here
+
+-- opaque refresh tokens, only the sha-256 hash of the token is stored
+-- every refresh rotates the token inside the same family, reusing an
+-- already rotated token revokes the whole family
+create table refresh_tokens (
+    token_hash varchar(64) not null,
+    family_id uuid not null,
+    user_id varchar(36) not null,
+    application_id uuid not null,
+    issued_at timestamp not null default now(),
+    expires_at timestamp not null,
+    used boolean not null default false,
+    revoked boolean not null default false,
+    primary key (token_hash),
+    foreign key (user_id) references users(user_id) on delete cascade,
+    foreign key (application_id) references login_applications(id) on delete cascade
+);
+
+create index refresh_tokens_family_id_idx on refresh_tokens (family_id);
@@ -0,0 +1,10 @@
+-- Your SQL goes here
+
+-- access tokens revoked before their expiration, identified by their jti claim
+-- the rows can be removed once the token expires
+create table revoked_tokens (
+    jwt_id varchar(64) not null,
+    expires_at timestamp not null,
+    revoked_at timestamp not null default now(),
+    primary key (jwt_id)
+);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+
+ALTER TABLE login_applications DROP COLUMN IF EXISTS client_secret_hash;
\ No newline at end of file
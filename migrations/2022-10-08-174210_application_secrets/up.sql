@@ -0,0 +1,5 @@
+-- Your SQL goes here
+
+-- the applications authenticate with a secret to introspect the tokens (argon2 hash)
+-- the applications without a secret can only request tokens
+alter table login_applications add column client_secret_hash varchar(255);
\ No newline at end of file
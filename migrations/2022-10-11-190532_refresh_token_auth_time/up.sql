@@ -0,0 +1,5 @@
+-- Your SQL goes here
+
+-- the time of the login that started the refresh token family,
+-- the access tokens issued on refresh keep the original auth_time claim
+alter table refresh_tokens add column auth_time timestamp not null default now();
\ No newline at end of file
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+
+DROP TABLE IF EXISTS pairwise_subjects;
+ALTER TABLE login_applications DROP COLUMN IF EXISTS pairwise_subject;
\ No newline at end of file
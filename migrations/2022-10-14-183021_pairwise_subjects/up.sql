@@ -0,0 +1,17 @@
+-- Your SQL goes here
+
+-- the applications with pairwise subjects get a pseudonymous subject per user
+-- instead of the global user id, so the applications can not correlate the users
+alter table login_applications add column pairwise_subject boolean not null default false;
+
+-- maps the pairwise subjects back to the users,
+-- the subject is the HMAC of the user subject and the application audience
+create table pairwise_subjects (
+    subject varchar(64) not null,
+    user_id varchar(36) not null,
+    application_id uuid not null,
+    primary key (subject),
+    unique (user_id, application_id),
+    foreign key (user_id) references users(user_id) on delete cascade,
+    foreign key (application_id) references login_applications(id) on delete cascade
+);
\ No newline at end of file
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+
+ALTER TABLE refresh_tokens DROP COLUMN IF EXISTS dpop_jkt;
\ No newline at end of file
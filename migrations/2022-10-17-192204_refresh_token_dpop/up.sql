@@ -0,0 +1,5 @@
+-- Your SQL goes here
+
+-- the thumbprint of the DPoP key the refresh token family is bound to (RFC 9449),
+-- the family can only be refreshed with a proof signed by this key
+alter table refresh_tokens add column dpop_jkt varchar;
\ No newline at end of file
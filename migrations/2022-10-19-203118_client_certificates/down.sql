@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+
+ALTER TABLE refresh_tokens DROP COLUMN IF EXISTS certificate_thumbprint;
+ALTER TABLE login_applications DROP COLUMN IF EXISTS certificate_thumbprint;
\ No newline at end of file
@@ -0,0 +1,8 @@
+-- Your SQL goes here
+
+-- the SHA-256 thumbprint (x5t#S256) of the client certificate of the application (RFC 8705),
+-- the application can authenticate with mutual TLS instead of its secret
+alter table login_applications add column certificate_thumbprint varchar(64);
+
+-- the thumbprint of the client certificate the refresh token family is bound to
+alter table refresh_tokens add column certificate_thumbprint varchar(64);
\ No newline at end of file
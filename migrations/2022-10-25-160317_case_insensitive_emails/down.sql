@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+
+alter table user_emails drop column email_normalized;
+alter table user_emails alter column email type varchar(254);
@@ -0,0 +1,17 @@
+-- Your SQL goes here
+
+-- the emails are unique without their case, `Alice@x.com` and `alice@x.com` are the same user,
+-- the existing emails differing only by their case must be merged before this migration
+create extension if not exists citext;
+
+alter table user_emails alter column email type citext;
+
+-- the canonical form of the email (trimmed, lowercase), used to find the users,
+-- the same as `normalize_email`: only the spaces are trimmed and only the ASCII letters
+-- are lowercased (`lower` would depend on the locale of the database)
+alter table user_emails add column email_normalized varchar(254);
+update user_emails
+set email_normalized = translate(trim(both ' ' from email),
+    'ABCDEFGHIJKLMNOPQRSTUVWXYZ', 'abcdefghijklmnopqrstuvwxyz');
+alter table user_emails alter column email_normalized set not null;
+alter table user_emails add constraint user_emails_email_normalized_key unique (email_normalized);
@@ -0,0 +1,9 @@
+-- This file should undo anything in `up.sql`
+
+drop table pairwise_subjects;
+drop table revoked_tokens;
+drop table refresh_tokens;
+drop table login_applications;
+drop table user_emails;
+drop table user_passwords;
+drop table users;
@@ -0,0 +1,76 @@
+-- Your SQL goes here
+
+-- the tables of the Postgres migrations up to `2022-10-19-203118_client_certificates`
+-- for the SQLite databases, the next changes are added to both migration directories,
+-- the uuids are stored as text and the times as unix timestamps (seconds)
+
+create table users (
+    user_id varchar(36) not null,
+    primary key (user_id)
+);
+
+create table user_passwords (
+    user_id varchar(36) not null,
+    password_hash varchar(255) not null,
+    primary key (user_id),
+    foreign key (user_id) references users(user_id) on delete cascade
+);
+
+create table user_emails (
+    user_id varchar(36) not null,
+    email varchar(254) not null unique,
+    primary key (user_id),
+    foreign key (user_id) references users(user_id) on delete cascade
+);
+
+-- a list of registered applications in the service,
+-- the id has no default value so it must be given when the application is registered
+create table login_applications (
+    id varchar(36) not null,
+    friendly_name varchar(255) not null,
+    audience varchar(255) not null,
+    callback_url varchar(255) not null,
+    client_secret_hash varchar(255),
+    pairwise_subject boolean not null default false,
+    certificate_thumbprint varchar(64),
+    primary key (id)
+);
+
+-- opaque refresh tokens, only the sha-256 hash of the token is stored
+create table refresh_tokens (
+    token_hash varchar(64) not null,
+    family_id varchar(36) not null,
+    user_id varchar(36) not null,
+    application_id varchar(36) not null,
+    issued_at bigint not null default (strftime('%s', 'now')),
+    expires_at bigint not null,
+    used boolean not null default false,
+    revoked boolean not null default false,
+    auth_time bigint not null default (strftime('%s', 'now')),
+    dpop_jkt varchar,
+    certificate_thumbprint varchar(64),
+    primary key (token_hash),
+    foreign key (user_id) references users(user_id) on delete cascade,
+    foreign key (application_id) references login_applications(id) on delete cascade
+);
+
+create index refresh_tokens_family_id_idx on refresh_tokens (family_id);
+
+-- access tokens revoked before their expiration, identified by their jti claim
+create table revoked_tokens (
+    jwt_id varchar(64) not null,
+    expires_at bigint not null,
+    revoked_at bigint not null default (strftime('%s', 'now')),
+    primary key (jwt_id)
+);
+
+-- maps the pairwise subjects back to the users
+create table pairwise_subjects (
+    subject varchar(64) not null,
+    user_id varchar(36) not null,
+    application_id varchar(36) not null,
+    primary key (subject),
+    unique (user_id, application_id),
+    foreign key (user_id) references users(user_id) on delete cascade,
+    foreign key (application_id) references login_applications(id) on delete cascade
+);
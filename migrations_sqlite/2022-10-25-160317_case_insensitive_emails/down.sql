@@ -0,0 +1,14 @@
+-- This file should undo anything in `up.sql`
+
+create table user_emails_original (
+    user_id varchar(36) not null,
+    email varchar(254) not null unique,
+    primary key (user_id),
+    foreign key (user_id) references users(user_id) on delete cascade
+);
+
+insert into user_emails_original (user_id, email)
+select user_id, email from user_emails;
+
+drop table user_emails;
+alter table user_emails_original rename to user_emails;
@@ -0,0 +1,21 @@
+-- Your SQL goes here
+
+-- the emails are unique without their case, `Alice@x.com` and `alice@x.com` are the same user,
+-- the existing emails differing only by their case must be merged before this migration
+-- (sqlite can not change a column, the table is created again)
+create table user_emails_normalized (
+    user_id varchar(36) not null,
+    email varchar(254) not null collate nocase unique,
+    -- the canonical form of the email (trimmed, lowercase), used to find the users,
+    -- the same as `normalize_email` as `trim` only removes the spaces
+    -- and `lower` only lowercases the ASCII letters
+    email_normalized varchar(254) not null unique,
+    primary key (user_id),
+    foreign key (user_id) references users(user_id) on delete cascade
+);
+
+insert into user_emails_normalized (user_id, email, email_normalized)
+select user_id, email, lower(trim(email)) from user_emails;
+
+drop table user_emails;
+alter table user_emails_normalized rename to user_emails;
@@ -0,0 +1,101 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use rocket::{
+    http::{RawStr, Status},
+    mtls::Certificate,
+    request::{FromRequest, Outcome},
+    Request,
+};
+use rocket_okapi::{
+    gen::OpenApiGenerator,
+    okapi::openapi3::{Object, SecurityRequirement, SecurityScheme, SecuritySchemeData},
+    request::{OpenApiFromRequest, RequestHeaderInput},
+};
+
+/// the credentials of the calling application sent with
+/// HTTP basic authentication (RFC 6749 section 2.3.1)
+pub(crate) struct ClientAuthorization {
+    pub client_id: String,
+    pub client_secret: String,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ClientAuthorization {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        match request
+            .headers()
+            .get_one("Authorization")
+            .and_then(parse_basic_authorization)
+        {
+            Some(client) => Outcome::Success(client),
+            None => Outcome::Error((Status::Unauthorized, ())),
+        }
+    }
+}
+
+// the id and the secret are form encoded before being joined with a colon
+fn parse_basic_authorization(header: &str) -> Option<ClientAuthorization> {
+    let encoded = header.strip_prefix("Basic ")?;
+    let decoded = String::from_utf8(STANDARD.decode(encoded.trim()).ok()?).ok()?;
+    let (client_id, client_secret) = decoded.split_once(':')?;
+    Some(ClientAuthorization {
+        client_id: RawStr::new(client_id).url_decode().ok()?.into_owned(),
+        client_secret: RawStr::new(client_secret).url_decode().ok()?.into_owned(),
+    })
+}
+
+impl<'r> OpenApiFromRequest<'r> for ClientAuthorization {
+    fn from_request_input(
+        _gen: &mut OpenApiGenerator,
+        _name: String,
+        _required: bool,
+    ) -> rocket_okapi::Result<RequestHeaderInput> {
+        let scheme = SecurityScheme {
+            description: Some("the id and the secret of the login application".to_string()),
+            data: SecuritySchemeData::Http {
+                scheme: "basic".to_string(),
+                bearer_format: None,
+            },
+            extensions: Object::default(),
+        };
+        let mut requirement = SecurityRequirement::new();
+        requirement.insert("ClientAuthorization".to_string(), Vec::new());
+        Ok(RequestHeaderInput::Security(
+            "ClientAuthorization".to_string(),
+            scheme,
+            requirement,
+        ))
+    }
+}
+
+/// the client certificate presented on the TLS connection (mutual TLS, RFC 8705),
+/// the certificate is verified against the `tls.mutual.ca_certs` of the rocket configuration
+pub(crate) struct ClientCertificate {
+    pub der: Vec<u8>,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ClientCertificate {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        match request.guard::<Certificate<'_>>().await {
+            Outcome::Success(certificate) => Outcome::Success(ClientCertificate {
+                der: certificate.as_bytes().to_vec(),
+            }),
+            _ => Outcome::Forward(Status::Unauthorized),
+        }
+    }
+}
+
+impl<'r> OpenApiFromRequest<'r> for ClientCertificate {
+    fn from_request_input(
+        _gen: &mut OpenApiGenerator,
+        _name: String,
+        _required: bool,
+    ) -> rocket_okapi::Result<RequestHeaderInput> {
+        // the certificate is part of the TLS connection, not of the request
+        Ok(RequestHeaderInput::None)
+    }
+}
@@ -0,0 +1,49 @@
+use auth_server_lib::api::endpoints;
+use colored::*;
+
+/// runs the command of the arguments (`rocket-server <command>`) and exits,
+/// returns when there is no command so the server is launched
+pub(crate) fn run_command() {
+    let command = match std::env::args().nth(1) {
+        Some(command) => command,
+        None => return,
+    };
+    let code = match command.as_str() {
+        "migrations" => print_migration_status(),
+        _ => {
+            eprintln!(
+                "{} {} (the available command is `migrations`)",
+                "Unknown command:".red(),
+                command
+            );
+            2
+        }
+    };
+    std::process::exit(code);
+}
+
+/// prints the migrations built in the server and whether they are applied to the database
+fn print_migration_status() -> i32 {
+    let status = endpoints::database_repository()
+        .and_then(|repository| endpoints::migration_status(&repository));
+    match status {
+        Ok(migrations) => {
+            println!("Migrations:");
+            for migration in &migrations {
+                match migration.applied {
+                    true => println!("  {} {}", "[X]".green(), migration.name),
+                    false => println!("  {} {}", "[ ]".yellow(), migration.name),
+                }
+            }
+            0
+        }
+        Err(e) => {
+            eprintln!(
+                "{} {}",
+                "Could not read the migrations:".red(),
+                e.internal_error.unwrap_or_else(|| e.message.to_string())
+            );
+            1
+        }
+    }
+}
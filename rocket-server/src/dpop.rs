@@ -0,0 +1,93 @@
+use auth_server_lib::api::errors::{ErrorDetails, ERR_INVALID_DPOP_PROOF};
+use rocket::{
+    http::Status,
+    request::{FromRequest, Outcome},
+    Request,
+};
+use rocket_okapi::{
+    gen::OpenApiGenerator,
+    okapi::openapi3::{Object, Parameter, ParameterValue},
+    request::{OpenApiFromRequest, RequestHeaderInput},
+};
+
+/// the DPoP proof (RFC 9449) sent in the `DPoP` header,
+/// with the method and the uri of the request it must be made for
+///
+/// the uri is built from the `public_url` setting of rocket (the address of the server
+/// as seen by the clients, behind a proxy) or from the `Host` header,
+/// a request without the header forwards, a header that can not be used is an error
+pub(crate) struct DpopHeader {
+    pub proof: String,
+    pub method: String,
+    pub uri: String,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for DpopHeader {
+    type Error = ErrorDetails;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let mut proofs = request.headers().get("DPoP");
+        let proof = match (proofs.next(), proofs.next()) {
+            (Some(proof), None) => proof,
+            // only a single proof is allowed
+            (Some(_), Some(_)) => return invalid_proof("only a single DPoP proof is allowed"),
+            (None, _) => return Outcome::Forward(Status::Unauthorized),
+        };
+        let base_url = match request
+            .rocket()
+            .figment()
+            .extract_inner::<String>("public_url")
+        {
+            Ok(public_url) => public_url,
+            Err(_) => match request.host() {
+                Some(host) => {
+                    let scheme = match request.rocket().config().tls_enabled() {
+                        true => "https",
+                        false => "http",
+                    };
+                    format!("{}://{}", scheme, host)
+                }
+                None => return invalid_proof("the request has no host"),
+            },
+        };
+        Outcome::Success(DpopHeader {
+            proof: proof.to_string(),
+            method: request.method().as_str().to_string(),
+            uri: format!("{}{}", base_url.trim_end_matches('/'), request.uri().path()),
+        })
+    }
+}
+
+fn invalid_proof(reason: &str) -> Outcome<DpopHeader, ErrorDetails> {
+    Outcome::Error((
+        Status::BadRequest,
+        ERR_INVALID_DPOP_PROOF.with_internal_error(reason.to_string()),
+    ))
+}
+
+impl<'r> OpenApiFromRequest<'r> for DpopHeader {
+    fn from_request_input(
+        gen: &mut OpenApiGenerator,
+        _name: String,
+        _required: bool,
+    ) -> rocket_okapi::Result<RequestHeaderInput> {
+        Ok(RequestHeaderInput::Parameter(Parameter {
+            name: "DPoP".to_string(),
+            location: "header".to_string(),
+            description: Some("a DPoP proof, the issued tokens are bound to its key".to_string()),
+            required: false,
+            deprecated: false,
+            allow_empty_value: false,
+            value: ParameterValue::Schema {
+                style: None,
+                explode: None,
+                allow_reserved: false,
+                schema: gen.json_schema::<String>(),
+                example: None,
+                examples: None,
+            },
+            extensions: Object::default(),
+        }))
+    }
+}
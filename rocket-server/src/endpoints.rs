@@ -1,26 +1,140 @@
-use auth_server_lib::api::{endpoints, model};
-use rocket_okapi::{openapi};
-
+use auth_server_lib::api::{endpoints, errors, model, repository::DatabaseRepository};
+use rocket_okapi::{
+    okapi::{schemars, schemars::JsonSchema},
+    openapi,
+};
 
+use crate::{
+    client_auth::{ClientAuthorization, ClientCertificate},
+    dpop::DpopHeader,
+};
 use rocket::{
+    form::Form,
     http::{ContentType, Status},
     serde::json::{serde_json::json, Json},
+    State,
 };
 
+// the failed response of an error detected before calling the endpoint
+fn failed(err: errors::ErrorDetails) -> (Status, (ContentType, serde_json::Value)) {
+    (
+        Status::new(err.http_code),
+        (
+            ContentType::JSON,
+            json!({
+                "result": "failed",
+                "error": err
+            }),
+        ),
+    )
+}
+
+fn dpop_request(dpop: &Option<DpopHeader>) -> Option<model::DpopRequest<'_>> {
+    dpop.as_ref().map(|dpop| model::DpopRequest {
+        proof: &dpop.proof,
+        method: &dpop.method,
+        uri: &dpop.uri,
+    })
+}
+
+fn client_certificate(
+    certificate: &Option<ClientCertificate>,
+) -> Option<model::ClientCertificate<'_>> {
+    certificate
+        .as_ref()
+        .map(|certificate| model::ClientCertificate {
+            der: &certificate.der,
+        })
+}
+
+/// token revocation request (RFC 7009), sent as a form
+#[derive(FromForm, JsonSchema)]
+pub(crate) struct RevocationForm<'r> {
+    token: &'r str,
+    token_type_hint: Option<&'r str>,
+    // the id of the application authenticating with its client certificate
+    client_id: Option<&'r str>,
+}
+
 #[openapi(tag = "Users")]
 #[post("/email/login", data = "<credentials>", format = "application/json")]
-pub(crate) fn login(
-    credentials: Json<model::UserCredentials<'_>>,
+pub(crate) async fn login(
+    repository: &State<DatabaseRepository>,
+    credentials: Json<model::LoginCredentials<'_>>,
+    dpop: Option<Result<DpopHeader, errors::ErrorDetails>>,
+    certificate: Option<ClientCertificate>,
 ) -> (Status, (ContentType, serde_json::Value)) {
+    // an unusable DPoP header rejects the request, only a request without it is unbound
+    let dpop = match dpop.transpose() {
+        Ok(dpop) => dpop,
+        Err(err) => return failed(err),
+    };
     // set the cors header
-    match endpoints::login(credentials.into_inner()) {
-        Ok(creds) => (
+    match endpoints::login(
+        repository.inner(),
+        credentials.into_inner(),
+        dpop_request(&dpop),
+        client_certificate(&certificate),
+    )
+    .await
+    {
+        Ok(tokens) => (
+            Status::Ok,
+            (
+                ContentType::JSON,
+                json!({
+                    "result": "success",
+                    "credentials": tokens.access_token,
+                    "refresh_token": tokens.refresh_token,
+                    "expires_in": tokens.expires_in,
+                    "token_type": tokens.token_type
+                }),
+            ),
+        ),
+        Err(err) => (
+            Status::new(err.http_code),
+            (
+                ContentType::JSON,
+                json!({
+                    "result": "failed",
+                    "error": err
+                }),
+            ),
+        ),
+    }
+}
+
+#[openapi(tag = "Tokens")]
+#[post("/token/refresh", data = "<request>", format = "application/json")]
+pub(crate) async fn refresh_token(
+    repository: &State<DatabaseRepository>,
+    request: Json<model::RefreshRequest<'_>>,
+    dpop: Option<Result<DpopHeader, errors::ErrorDetails>>,
+    certificate: Option<ClientCertificate>,
+) -> (Status, (ContentType, serde_json::Value)) {
+    // an unusable DPoP header rejects the request, only a request without it is unbound
+    let dpop = match dpop.transpose() {
+        Ok(dpop) => dpop,
+        Err(err) => return failed(err),
+    };
+    match endpoints::refresh(
+        repository.inner(),
+        request.into_inner(),
+        dpop_request(&dpop),
+        client_certificate(&certificate),
+    )
+    .await
+    {
+        Ok(tokens) => (
             Status::Ok,
             (
                 ContentType::JSON,
                 json!({
                     "result": "success",
-                    "credentials": creds
+                    "credentials": tokens.access_token,
+                    "refresh_token": tokens.refresh_token,
+                    "expires_in": tokens.expires_in,
+                    "token_type": tokens.token_type
                 }),
             ),
         ),
@@ -37,12 +151,207 @@ pub(crate) fn login(
     }
 }
 
+/// the calling application authenticates like for the introspection
+/// and can only revoke its own tokens
+#[openapi(tag = "Tokens")]
+#[post(
+    "/token/revoke",
+    data = "<request>",
+    format = "application/x-www-form-urlencoded"
+)]
+pub(crate) async fn revoke_token(
+    repository: &State<DatabaseRepository>,
+    client: Option<ClientAuthorization>,
+    certificate: Option<ClientCertificate>,
+    request: Form<RevocationForm<'_>>,
+) -> (Status, (ContentType, serde_json::Value)) {
+    let result = match client_authentication(&client, &certificate, request.client_id) {
+        Some(client) => {
+            endpoints::revoke(
+                repository.inner(),
+                client,
+                model::RevocationRequest {
+                    token: request.token,
+                    token_type_hint: request.token_type_hint,
+                },
+            )
+            .await
+        }
+        None => Err(errors::ERR_CLIENT_AUTHENTICATION_FAILED
+            .with_internal_error("missing client authentication".to_string())),
+    };
+    match result {
+        Ok(_) => (
+            Status::Ok,
+            (
+                ContentType::JSON,
+                json!({
+                    "result": "success"
+                }),
+            ),
+        ),
+        Err(err) => (
+            Status::new(err.http_code),
+            (
+                ContentType::JSON,
+                json!({
+                    "result": "failed",
+                    "error": err
+                }),
+            ),
+        ),
+    }
+}
+
+/// token introspection request (RFC 7662), sent as a form
+#[derive(FromForm, JsonSchema)]
+pub(crate) struct IntrospectionForm<'r> {
+    token: &'r str,
+    token_type_hint: Option<&'r str>,
+    // the id of the application authenticating with its client certificate
+    client_id: Option<&'r str>,
+}
+
+/// the calling application authenticates with HTTP basic authentication
+/// or with its client certificate (mutual TLS) and its `client_id` in the form,
+/// the response is the plain RFC 7662 json object
+#[openapi(tag = "Tokens")]
+#[post(
+    "/introspect",
+    data = "<request>",
+    format = "application/x-www-form-urlencoded"
+)]
+pub(crate) async fn introspect_token(
+    repository: &State<DatabaseRepository>,
+    client: Option<ClientAuthorization>,
+    certificate: Option<ClientCertificate>,
+    request: Form<IntrospectionForm<'_>>,
+) -> (Status, (ContentType, serde_json::Value)) {
+    let result = match client_authentication(&client, &certificate, request.client_id) {
+        Some(client) => {
+            endpoints::introspect(
+                repository.inner(),
+                client,
+                model::IntrospectionRequest {
+                    token: request.token,
+                    token_type_hint: request.token_type_hint,
+                },
+            )
+            .await
+        }
+        None => Err(errors::ERR_CLIENT_AUTHENTICATION_FAILED
+            .with_internal_error("missing client authentication".to_string())),
+    };
+    match result {
+        Ok(response) => (Status::Ok, (ContentType::JSON, json!(response))),
+        Err(err) => (
+            Status::new(err.http_code),
+            (
+                ContentType::JSON,
+                json!({
+                    "result": "failed",
+                    "error": err
+                }),
+            ),
+        ),
+    }
+}
+
+/// token exchange request (RFC 8693), sent as a form
+#[derive(FromForm, JsonSchema)]
+pub(crate) struct TokenExchangeForm<'r> {
+    grant_type: &'r str,
+    subject_token: &'r str,
+    subject_token_type: &'r str,
+    audience: &'r str,
+    scope: Option<&'r str>,
+    // the id of the application authenticating with its client certificate
+    client_id: Option<&'r str>,
+}
+
+/// exchanges an access token of the calling application for a token of another audience,
+/// the application authenticates like for the introspection,
+/// the response is the plain RFC 8693 json object
+#[openapi(tag = "Tokens")]
+#[post(
+    "/token/exchange",
+    data = "<request>",
+    format = "application/x-www-form-urlencoded"
+)]
+pub(crate) async fn exchange_token(
+    repository: &State<DatabaseRepository>,
+    client: Option<ClientAuthorization>,
+    certificate: Option<ClientCertificate>,
+    request: Form<TokenExchangeForm<'_>>,
+) -> (Status, (ContentType, serde_json::Value)) {
+    let result = match client_authentication(&client, &certificate, request.client_id) {
+        Some(client) => {
+            endpoints::exchange_token(
+                repository.inner(),
+                client,
+                model::TokenExchangeRequest {
+                    grant_type: request.grant_type,
+                    subject_token: request.subject_token,
+                    subject_token_type: request.subject_token_type,
+                    audience: request.audience,
+                    scope: request.scope,
+                },
+            )
+            .await
+        }
+        None => Err(errors::ERR_CLIENT_AUTHENTICATION_FAILED
+            .with_internal_error("missing client authentication".to_string())),
+    };
+    match result {
+        Ok(response) => (Status::Ok, (ContentType::JSON, json!(response))),
+        Err(err) => (
+            Status::new(err.http_code),
+            (
+                ContentType::JSON,
+                json!({
+                    "result": "failed",
+                    "error": err
+                }),
+            ),
+        ),
+    }
+}
+
+/// the basic authorization of the application,
+/// or its client certificate with the `client_id` sent in the form
+fn client_authentication<'a>(
+    client: &'a Option<ClientAuthorization>,
+    certificate: &'a Option<ClientCertificate>,
+    client_id: Option<&'a str>,
+) -> Option<model::ClientAuthentication<'a>> {
+    match (client, certificate, client_id) {
+        (Some(client), _, _) => Some(model::ClientAuthentication::Secret(
+            model::ClientCredentials {
+                client_id: &client.client_id,
+                client_secret: &client.client_secret,
+            },
+        )),
+        (None, Some(certificate), Some(client_id)) => {
+            Some(model::ClientAuthentication::Certificate {
+                client_id,
+                certificate: model::ClientCertificate {
+                    der: &certificate.der,
+                },
+            })
+        }
+        _ => None,
+    }
+}
+
 #[openapi(tag = "Users")]
 #[post("/email/register", data = "<credentials>", format = "application/json")]
-pub(crate) fn register_by_email_password(
+pub(crate) async fn register_by_email_password(
+    repository: &State<DatabaseRepository>,
     credentials: Json<model::UserCredentials<'_>>,
 ) -> (Status, (ContentType, serde_json::Value)) {
-    match endpoints::register_new_user_email_password(credentials.into_inner()) {
+    match endpoints::register_new_user_email_password(repository.inner(), credentials.into_inner())
+        .await
+    {
         Ok(user_id) => (
             Status::Ok,
             (
@@ -80,3 +389,25 @@ pub(crate) fn register_by_email_password(
         }
     }
 }
+
+#[openapi(tag = "Keys")]
+#[get("/.well-known/jwks.json")]
+pub(crate) fn jwks() -> (Status, (ContentType, serde_json::Value)) {
+    let jwks = endpoints::get_jwks().and_then(|jwks| {
+        serde_json::from_str(&jwks)
+            .map_err(|e| errors::ERR_UNKNOWN_INTERNAL_ERROR.with_internal_error(e.to_string()))
+    });
+    match jwks {
+        Ok(jwks) => (Status::Ok, (ContentType::JSON, jwks)),
+        Err(err) => (
+            Status::new(err.http_code),
+            (
+                ContentType::JSON,
+                json!({
+                    "result": "failed",
+                    "error": err
+                }),
+            ),
+        ),
+    }
+}
@@ -6,7 +6,12 @@ extern crate colored;
 use colored::*;
 
 mod catchers;
+mod client_auth;
+mod commands;
+mod dpop;
 mod endpoints;
+#[cfg(test)]
+mod tests;
 
 use endpoints::*;
 
@@ -15,16 +20,68 @@ use rocket_okapi::{openapi_get_routes, rapidoc::*, settings::UrlObject, swagger_
 
 #[launch]
 fn rocket() -> _ {
+    // `rocket-server migrations` prints the status of the migrations instead of serving
+    commands::run_command();
+    // the token keys are resolved once, an invalid configuration stops the server here
+    if let Err(e) = auth_server_lib::api::endpoints::load_configuration() {
+        eprintln!(
+            "{} {}",
+            "Invalid configuration:".red(),
+            e.internal_error.unwrap_or_else(|| e.message.to_string())
+        );
+        std::process::exit(1);
+    }
+    // the connections to the database are shared by the requests
+    let repository = match auth_server_lib::api::endpoints::database_repository() {
+        Ok(repository) => repository,
+        Err(e) => {
+            eprintln!(
+                "{} {}",
+                "Invalid database configuration:".red(),
+                e.internal_error.unwrap_or_else(|| e.message.to_string())
+            );
+            std::process::exit(1);
+        }
+    };
+    // a fresh database is usable without the diesel cli with `DATABASE_MIGRATE_ON_START`
+    match auth_server_lib::api::endpoints::migrate_on_start(&repository) {
+        Ok(applied) => {
+            for name in applied {
+                println!("Applied the migration {}", name.green());
+            }
+        }
+        Err(e) => {
+            eprintln!(
+                "{} {}",
+                "Could not migrate the database:".red(),
+                e.internal_error.unwrap_or_else(|| e.message.to_string())
+            );
+            std::process::exit(1);
+        }
+    }
     let base_url = "/auth";
     let openapi_json_url = format!("{}/openapi.json", base_url);
-    let rocket_app =
-        rocket::build().register("/", catchers![not_found, bad_request, unprocessable_entity]);
+    let rocket_app = rocket::build()
+        .manage(repository)
+        .register("/", catchers![not_found, bad_request, unprocessable_entity])
+        // the key set is served from the well-known location at the root
+        .mount("/", routes![jwks]);
     match cfg!(debug_assertions) {
         false => {
             println!("{}", "*************************************".cyan());
             println!("Running in {} mode", "production".green());
             println!("{}", "*************************************".cyan());
-            rocket_app.mount(base_url, routes![login, register_by_email_password])
+            rocket_app.mount(
+                base_url,
+                routes![
+                    login,
+                    refresh_token,
+                    revoke_token,
+                    introspect_token,
+                    exchange_token,
+                    register_by_email_password
+                ],
+            )
         }
         true => {
             println!("{}", "*************************************".cyan());
@@ -33,7 +90,14 @@ fn rocket() -> _ {
             rocket_app
                 .mount(
                     base_url,
-                    openapi_get_routes![login, register_by_email_password],
+                    openapi_get_routes![
+                        login,
+                        refresh_token,
+                        revoke_token,
+                        introspect_token,
+                        exchange_token,
+                        register_by_email_password
+                    ],
                 )
                 .mount(
                     format!("{}/api/v1/swagger-ui/", base_url),
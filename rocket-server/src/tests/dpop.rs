@@ -0,0 +1,52 @@
+use auth_server_lib::api::{
+    errors::ERR_INVALID_DPOP_PROOF,
+    repository::{DatabaseRepository, PgRepository},
+};
+use dboilerplate::util::pool::{create_pool, PoolConfig};
+use rocket::{
+    http::{ContentType, Header, Status},
+    local::blocking::Client,
+    serde::json::serde_json::{json, Value},
+};
+
+use crate::endpoints::{login, refresh_token};
+
+// the requests are rejected before the database is used
+fn client() -> Client {
+    let pool = create_pool("postgres://localhost/unused", &PoolConfig::default());
+    let repository = DatabaseRepository::Postgres(PgRepository::new(pool));
+    let rocket = rocket::build()
+        .manage(repository)
+        .mount("/auth", routes![login, refresh_token]);
+    Client::tracked(rocket).unwrap()
+}
+
+#[test]
+fn test_several_dpop_proofs() {
+    let client = client();
+    let requests = [
+        (
+            "/auth/email/login",
+            json!({
+                "email": "test@example.com",
+                "password": "password",
+                "application_id": "00000000-0000-0000-0000-000000000000"
+            }),
+        ),
+        ("/auth/token/refresh", json!({ "refresh_token": "token" })),
+    ];
+    for (uri, body) in requests {
+        // the request is not served as an unbound request
+        let response = client
+            .post(uri)
+            .header(ContentType::JSON)
+            .header(Header::new("DPoP", "first-proof"))
+            .header(Header::new("DPoP", "second-proof"))
+            .body(body.to_string())
+            .dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+        let body: Value = response.into_json().unwrap();
+        assert_eq!(body["result"], "failed");
+        assert_eq!(body["error"]["code_name"], ERR_INVALID_DPOP_PROOF.code_name);
+    }
+}
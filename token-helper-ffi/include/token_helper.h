@@ -0,0 +1,187 @@
+#ifndef TOKEN_HELPER_H
+#define TOKEN_HELPER_H
+
+/* generated by cbindgen from src/lib.rs, do not edit */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/*
+ how a key is used to read the tokens
+ */
+typedef enum TokenHelperKeyType {
+  /*
+   the public Ed25519 key of the issuer, for the EdDSA signed tokens
+   */
+  TOKEN_HELPER_KEY_TYPE_ED25519 = 1,
+  /*
+   the public P-256 key of the issuer, for the ES256 signed tokens
+   */
+  TOKEN_HELPER_KEY_TYPE_P256 = 2,
+  /*
+   the JWK Set published by the issuer (json), for the tokens signed with its key ring
+   */
+  TOKEN_HELPER_KEY_TYPE_JWK_SET = 3,
+} TokenHelperKeyType;
+
+/*
+ the result of the functions of the library
+ */
+typedef enum TokenHelperStatus {
+  TOKEN_HELPER_STATUS_OK = 0,
+  /*
+   a null pointer or a string that is not valid UTF-8
+   */
+  TOKEN_HELPER_STATUS_INVALID_ARGUMENT = 1,
+  /*
+   the key could not be loaded or can not be used for the token
+   */
+  TOKEN_HELPER_STATUS_INVALID_KEY = 2,
+  /*
+   the token could not be decrypted, its signature or its header is invalid
+   */
+  TOKEN_HELPER_STATUS_INVALID_TOKEN = 3,
+  /*
+   a claim is missing or has an unexpected value
+   */
+  TOKEN_HELPER_STATUS_INVALID_CLAIM = 4,
+  TOKEN_HELPER_STATUS_INVALID_ISSUER = 5,
+  TOKEN_HELPER_STATUS_INVALID_AUDIENCE = 6,
+  TOKEN_HELPER_STATUS_EXPIRED = 7,
+  /*
+   the token is used before its not before time or issued in the future
+   */
+  TOKEN_HELPER_STATUS_NOT_YET_VALID = 8,
+  /*
+   the subject is not a valid user data
+   */
+  TOKEN_HELPER_STATUS_INVALID_SUBJECT = 9,
+  /*
+   the token is bound to a DPoP key or to another client certificate
+   */
+  TOKEN_HELPER_STATUS_INVALID_BINDING = 10,
+} TokenHelperStatus;
+
+/*
+ the claims of a verified token
+ */
+typedef struct TokenHelperClaims TokenHelperClaims;
+
+/*
+ a key loaded by `token_helper_load_key` or `token_helper_load_nested_key`
+ */
+typedef struct TokenHelperKey TokenHelperKey;
+
+/*
+ loads a PEM key, the key is only checked when a token is verified,
+ or the json of a JWK Set that is parsed when loaded
+
+ returns null if the key is null or empty, or if the JWK Set is invalid
+
+ # Safety
+ `pem` must point to `pem_len` readable bytes
+ */
+struct TokenHelperKey *token_helper_load_key(const uint8_t *pem,
+                                             size_t pem_len,
+                                             enum TokenHelperKeyType key_type);
+
+/*
+ loads the PEM private key of the service and the JWK Set published by the issuer (json),
+ for the tokens signed by the issuer and then encrypted for the service (nested tokens),
+ the tokens must use the default encryption algorithms (ECDH-ES with A128CBC-HS256)
+
+ returns null if a key is null or empty, or if the JWK Set is invalid
+
+ # Safety
+ `pem` must point to `pem_len` readable bytes and `jwk_set` to `jwk_set_len` readable bytes
+ */
+struct TokenHelperKey *token_helper_load_nested_key(const uint8_t *pem,
+                                                    size_t pem_len,
+                                                    const uint8_t *jwk_set,
+                                                    size_t jwk_set_len);
+
+/*
+ frees a key returned by `token_helper_load_key` or `token_helper_load_nested_key`
+
+ # Safety
+ `key` must be null or a key returned by `token_helper_load_key`
+ or `token_helper_load_nested_key` not freed yet
+ */
+void token_helper_key_free(struct TokenHelperKey *key);
+
+/*
+ verifies a token and validates its claims, on success `claims` is set
+ to the claims of the token that must be freed with `token_helper_claims_free`
+
+ # Safety
+ `key` must be a key returned by `token_helper_load_key` or `token_helper_load_nested_key`,
+ `token` and `issuer` nul terminated strings, `audiences` an array of `audiences_len`
+ nul terminated strings and `claims` a valid pointer
+ */
+enum TokenHelperStatus token_helper_verify(const struct TokenHelperKey *key,
+                                           const char *token,
+                                           const char *issuer,
+                                           const char *const *audiences,
+                                           size_t audiences_len,
+                                           struct TokenHelperClaims **claims);
+
+/*
+ verifies a token like `token_helper_verify`, the token may also be bound (`cnf.x5t#S256`)
+ to the DER encoded client certificate presented on the TLS connection
+
+ # Safety
+ the arguments of `token_helper_verify`, and `certificate` must point to
+ `certificate_len` readable bytes
+ */
+enum TokenHelperStatus token_helper_verify_with_certificate(const struct TokenHelperKey *key,
+                                                            const char *token,
+                                                            const char *issuer,
+                                                            const char *const *audiences,
+                                                            size_t audiences_len,
+                                                            const uint8_t *certificate,
+                                                            size_t certificate_len,
+                                                            struct TokenHelperClaims **claims);
+
+/*
+ the user id of the subject, valid until the claims are freed
+
+ # Safety
+ `claims` must be claims returned by `token_helper_verify`
+ */
+const char *token_helper_claims_user_id(const struct TokenHelperClaims *claims);
+
+/*
+ the realm of the subject, valid until the claims are freed
+
+ # Safety
+ `claims` must be claims returned by `token_helper_verify`
+ */
+const char *token_helper_claims_realm(const struct TokenHelperClaims *claims);
+
+/*
+ the custom claims of the token (roles, scope, amr...) as a json object,
+ valid until the claims are freed
+
+ # Safety
+ `claims` must be claims returned by `token_helper_verify`
+ */
+const char *token_helper_claims_json(const struct TokenHelperClaims *claims);
+
+/*
+ frees the claims returned by `token_helper_verify`
+
+ # Safety
+ `claims` must be null or claims returned by `token_helper_verify` not freed yet
+ */
+void token_helper_claims_free(struct TokenHelperClaims *claims);
+
+/*
+ the message of the last error of the calling thread,
+ valid until the next call to the library on the thread
+ */
+const char *token_helper_last_error(void);
+
+#endif /* TOKEN_HELPER_H */
@@ -0,0 +1,414 @@
+//! C API of the token helper, for the services that can not link the rust crate
+//!
+//! the tokens are validated with the rules of `token_helper::manager::decode_user_data`
+//! (issuer, audiences, expiration, not before and issue time, no leeway),
+//! the tokens bound to a DPoP key or a client certificate (`cnf`) are rejected
+//! unless the client certificate is given to `token_helper_verify_with_certificate`,
+//! the header is published in `include/token_helper.h`, it is generated by the build
+//! in the `OUT_DIR` of the crate and must be copied there when the API changes
+//!
+//! the tokens must be signed by the issuer, the tokens encrypted for the service are
+//! read with `token_helper_load_nested_key`, an encrypted token that is not signed
+//! is not authentic as anyone can encrypt it with the public key of the service
+//!
+//! ```c
+//! TokenHelperKey *key = token_helper_load_key(jwks, jwks_len, TOKEN_HELPER_KEY_TYPE_JWK_SET);
+//! const char *audiences[] = {"my-service"};
+//! TokenHelperClaims *claims = NULL;
+//! if (token_helper_verify(key, token, "issuer", audiences, 1, &claims) == TOKEN_HELPER_STATUS_OK) {
+//!     printf("%s\n", token_helper_claims_user_id(claims));
+//!     token_helper_claims_free(claims);
+//! } else {
+//!     printf("%s\n", token_helper_last_error());
+//! }
+//! token_helper_key_free(key);
+//! ```
+
+use std::{
+    cell::RefCell,
+    ffi::{c_char, CStr, CString},
+    ptr,
+};
+
+use josekit::jwk::JwkSet;
+use token_helper::{
+    claims::{CustomClaims, TokenClaims},
+    error::TokenError,
+    manager::{decode_token, DecodingKey},
+    mtls::certificate_thumbprint,
+    token_helper::SigningAlgorithm,
+    user::UserData,
+    validation::ValidationOptions,
+};
+
+thread_local! {
+    /// the message of the last error of the thread
+    static LAST_ERROR: RefCell<CString> = RefCell::new(CString::default());
+}
+
+/// the result of the functions of the library
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenHelperStatus {
+    Ok = 0,
+    /// a null pointer or a string that is not valid UTF-8
+    InvalidArgument = 1,
+    /// the key could not be loaded or can not be used for the token
+    InvalidKey = 2,
+    /// the token could not be decrypted, its signature or its header is invalid
+    InvalidToken = 3,
+    /// a claim is missing or has an unexpected value
+    InvalidClaim = 4,
+    InvalidIssuer = 5,
+    InvalidAudience = 6,
+    Expired = 7,
+    /// the token is used before its not before time or issued in the future
+    NotYetValid = 8,
+    /// the subject is not a valid user data
+    InvalidSubject = 9,
+    /// the token is bound to a DPoP key or to another client certificate
+    InvalidBinding = 10,
+}
+
+/// how a key is used to read the tokens
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenHelperKeyType {
+    /// the public Ed25519 key of the issuer, for the EdDSA signed tokens
+    Ed25519 = 1,
+    /// the public P-256 key of the issuer, for the ES256 signed tokens
+    P256 = 2,
+    /// the JWK Set published by the issuer (json), for the tokens signed with its key ring
+    JwkSet = 3,
+}
+
+/// a key loaded by `token_helper_load_key` or `token_helper_load_nested_key`
+pub struct TokenHelperKey(LoadedKey);
+
+enum LoadedKey {
+    /// the PEM public key of the issuer
+    Public(Vec<u8>, SigningAlgorithm),
+    /// the JWK Set published by the issuer
+    JwkSet(JwkSet),
+    /// the PEM private key of the audience and the JWK Set published by the issuer
+    Nested(Vec<u8>, JwkSet),
+}
+
+/// the claims of a verified token
+pub struct TokenHelperClaims {
+    user_id: CString,
+    realm: CString,
+    claims: CString,
+}
+
+/// loads a PEM key, the key is only checked when a token is verified,
+/// or the json of a JWK Set that is parsed when loaded
+///
+/// returns null if the key is null or empty, or if the JWK Set is invalid
+///
+/// # Safety
+/// `pem` must point to `pem_len` readable bytes
+#[no_mangle]
+pub unsafe extern "C" fn token_helper_load_key(
+    pem: *const u8,
+    pem_len: usize,
+    key_type: TokenHelperKeyType,
+) -> *mut TokenHelperKey {
+    if pem.is_null() || pem_len == 0 {
+        set_last_error("no key");
+        return ptr::null_mut();
+    }
+    let pem = std::slice::from_raw_parts(pem, pem_len).to_vec();
+    let key = match key_type {
+        TokenHelperKeyType::Ed25519 => LoadedKey::Public(pem, SigningAlgorithm::EdDSA),
+        TokenHelperKeyType::P256 => LoadedKey::Public(pem, SigningAlgorithm::ES256),
+        TokenHelperKeyType::JwkSet => match read_jwk_set(&pem) {
+            Some(jwk_set) => LoadedKey::JwkSet(jwk_set),
+            None => return ptr::null_mut(),
+        },
+    };
+    Box::into_raw(Box::new(TokenHelperKey(key)))
+}
+
+/// loads the PEM private key of the service and the JWK Set published by the issuer (json),
+/// for the tokens signed by the issuer and then encrypted for the service (nested tokens),
+/// the tokens must use the default encryption algorithms (ECDH-ES with A128CBC-HS256)
+///
+/// returns null if a key is null or empty, or if the JWK Set is invalid
+///
+/// # Safety
+/// `pem` must point to `pem_len` readable bytes and `jwk_set` to `jwk_set_len` readable bytes
+#[no_mangle]
+pub unsafe extern "C" fn token_helper_load_nested_key(
+    pem: *const u8,
+    pem_len: usize,
+    jwk_set: *const u8,
+    jwk_set_len: usize,
+) -> *mut TokenHelperKey {
+    if pem.is_null() || pem_len == 0 || jwk_set.is_null() || jwk_set_len == 0 {
+        set_last_error("no key");
+        return ptr::null_mut();
+    }
+    let pem = std::slice::from_raw_parts(pem, pem_len).to_vec();
+    match read_jwk_set(std::slice::from_raw_parts(jwk_set, jwk_set_len)) {
+        Some(jwk_set) => Box::into_raw(Box::new(TokenHelperKey(LoadedKey::Nested(pem, jwk_set)))),
+        None => ptr::null_mut(),
+    }
+}
+
+/// frees a key returned by `token_helper_load_key` or `token_helper_load_nested_key`
+///
+/// # Safety
+/// `key` must be null or a key returned by `token_helper_load_key`
+/// or `token_helper_load_nested_key` not freed yet
+#[no_mangle]
+pub unsafe extern "C" fn token_helper_key_free(key: *mut TokenHelperKey) {
+    if !key.is_null() {
+        drop(Box::from_raw(key));
+    }
+}
+
+/// verifies a token and validates its claims, on success `claims` is set
+/// to the claims of the token that must be freed with `token_helper_claims_free`
+///
+/// # Safety
+/// `key` must be a key returned by `token_helper_load_key` or `token_helper_load_nested_key`,
+/// `token` and `issuer` nul terminated strings, `audiences` an array of `audiences_len`
+/// nul terminated strings and `claims` a valid pointer
+#[no_mangle]
+pub unsafe extern "C" fn token_helper_verify(
+    key: *const TokenHelperKey,
+    token: *const c_char,
+    issuer: *const c_char,
+    audiences: *const *const c_char,
+    audiences_len: usize,
+    claims: *mut *mut TokenHelperClaims,
+) -> TokenHelperStatus {
+    verify_with_thumbprint(key, token, issuer, audiences, audiences_len, None, claims)
+}
+
+/// verifies a token like `token_helper_verify`, the token may also be bound (`cnf.x5t#S256`)
+/// to the DER encoded client certificate presented on the TLS connection
+///
+/// # Safety
+/// the arguments of `token_helper_verify`, and `certificate` must point to
+/// `certificate_len` readable bytes
+#[no_mangle]
+pub unsafe extern "C" fn token_helper_verify_with_certificate(
+    key: *const TokenHelperKey,
+    token: *const c_char,
+    issuer: *const c_char,
+    audiences: *const *const c_char,
+    audiences_len: usize,
+    certificate: *const u8,
+    certificate_len: usize,
+    claims: *mut *mut TokenHelperClaims,
+) -> TokenHelperStatus {
+    if certificate.is_null() || certificate_len == 0 {
+        return invalid_argument("no client certificate");
+    }
+    let x5t_s256 = certificate_thumbprint(std::slice::from_raw_parts(certificate, certificate_len));
+    verify_with_thumbprint(
+        key,
+        token,
+        issuer,
+        audiences,
+        audiences_len,
+        Some(&x5t_s256),
+        claims,
+    )
+}
+
+/// verifies a token bound to the certificate of the thumbprint, if any
+unsafe fn verify_with_thumbprint(
+    key: *const TokenHelperKey,
+    token: *const c_char,
+    issuer: *const c_char,
+    audiences: *const *const c_char,
+    audiences_len: usize,
+    certificate_thumbprint: Option<&str>,
+    claims: *mut *mut TokenHelperClaims,
+) -> TokenHelperStatus {
+    if key.is_null() || claims.is_null() || (audiences.is_null() && audiences_len > 0) {
+        return invalid_argument("null argument");
+    }
+    let key = &*key;
+    let (token, issuer) = match (read_str(token), read_str(issuer)) {
+        (Some(token), Some(issuer)) => (token, issuer),
+        _ => return invalid_argument("the token and the issuer must be UTF-8 strings"),
+    };
+    let mut accepted_audiences = Vec::with_capacity(audiences_len);
+    for i in 0..audiences_len {
+        match read_str(*audiences.add(i)) {
+            Some(audience) => accepted_audiences.push(audience),
+            None => return invalid_argument("the audiences must be UTF-8 strings"),
+        }
+    }
+    // the options of decode_user_data
+    let mut options = ValidationOptions::new(issuer, &accepted_audiences);
+    if let Some(x5t_s256) = certificate_thumbprint {
+        options = options.with_certificate_thumbprint(x5t_s256);
+    }
+    match verify(key, token, &options).and_then(|claims| {
+        TokenHelperClaims::new(claims.user_data, &claims.custom)
+            .ok_or_else(|| TokenError::InvalidClaim("the claims contain a nul byte".to_string()))
+    }) {
+        Ok(verified) => {
+            *claims = Box::into_raw(Box::new(verified));
+            TokenHelperStatus::Ok
+        }
+        Err(e) => {
+            set_last_error(&e.to_string());
+            status(&e)
+        }
+    }
+}
+
+/// the user id of the subject, valid until the claims are freed
+///
+/// # Safety
+/// `claims` must be claims returned by `token_helper_verify`
+#[no_mangle]
+pub unsafe extern "C" fn token_helper_claims_user_id(
+    claims: *const TokenHelperClaims,
+) -> *const c_char {
+    match claims.as_ref() {
+        Some(claims) => claims.user_id.as_ptr(),
+        None => ptr::null(),
+    }
+}
+
+/// the realm of the subject, valid until the claims are freed
+///
+/// # Safety
+/// `claims` must be claims returned by `token_helper_verify`
+#[no_mangle]
+pub unsafe extern "C" fn token_helper_claims_realm(
+    claims: *const TokenHelperClaims,
+) -> *const c_char {
+    match claims.as_ref() {
+        Some(claims) => claims.realm.as_ptr(),
+        None => ptr::null(),
+    }
+}
+
+/// the custom claims of the token (roles, scope, amr...) as a json object,
+/// valid until the claims are freed
+///
+/// # Safety
+/// `claims` must be claims returned by `token_helper_verify`
+#[no_mangle]
+pub unsafe extern "C" fn token_helper_claims_json(
+    claims: *const TokenHelperClaims,
+) -> *const c_char {
+    match claims.as_ref() {
+        Some(claims) => claims.claims.as_ptr(),
+        None => ptr::null(),
+    }
+}
+
+/// frees the claims returned by `token_helper_verify`
+///
+/// # Safety
+/// `claims` must be null or claims returned by `token_helper_verify` not freed yet
+#[no_mangle]
+pub unsafe extern "C" fn token_helper_claims_free(claims: *mut TokenHelperClaims) {
+    if !claims.is_null() {
+        drop(Box::from_raw(claims));
+    }
+}
+
+/// the message of the last error of the calling thread,
+/// valid until the next call to the library on the thread
+#[no_mangle]
+pub extern "C" fn token_helper_last_error() -> *const c_char {
+    LAST_ERROR.with(|last_error| last_error.borrow().as_ptr())
+}
+
+impl TokenHelperClaims {
+    fn new(user_data: UserData, custom: &CustomClaims) -> Option<Self> {
+        let claims = custom.to_json().ok()?.to_string();
+        Some(Self {
+            user_id: CString::new(user_data.user_id).ok()?,
+            realm: CString::new(user_data.realm).ok()?,
+            claims: CString::new(claims).ok()?,
+        })
+    }
+}
+
+impl LoadedKey {
+    fn decoding_key(&self) -> DecodingKey<'_> {
+        match self {
+            LoadedKey::Public(public_key, algorithm) => DecodingKey::Verify {
+                public_key: public_key.clone(),
+                algorithm: *algorithm,
+            },
+            LoadedKey::JwkSet(jwk_set) => DecodingKey::JwkSet(jwk_set),
+            LoadedKey::Nested(private_key, jwk_set) => DecodingKey::NestedJwkSet {
+                private_key: private_key.clone(),
+                jwk_set,
+            },
+        }
+    }
+}
+
+fn verify(
+    key: &TokenHelperKey,
+    token: &str,
+    options: &ValidationOptions,
+) -> Result<TokenClaims, TokenError> {
+    decode_token(&key.0.decoding_key(), token, options, None)
+}
+
+fn read_jwk_set(json: &[u8]) -> Option<JwkSet> {
+    match JwkSet::from_bytes(json) {
+        Ok(jwk_set) => Some(jwk_set),
+        Err(e) => {
+            set_last_error(&format!("invalid JWK Set: {}", e));
+            None
+        }
+    }
+}
+
+fn status(error: &TokenError) -> TokenHelperStatus {
+    match error {
+        TokenError::InvalidKey(_) | TokenError::UnknownKey(_) => TokenHelperStatus::InvalidKey,
+        TokenError::MalformedHeader(_)
+        | TokenError::Decryption(_)
+        | TokenError::InvalidSignature(_)
+        | TokenError::Encoding(_) => TokenHelperStatus::InvalidToken,
+        TokenError::MissingClaim(_)
+        | TokenError::ForbiddenClaim(_)
+        | TokenError::InvalidClaim(_)
+        | TokenError::ReservedClaim(_) => TokenHelperStatus::InvalidClaim,
+        TokenError::InvalidProof(_) => TokenHelperStatus::InvalidBinding,
+        TokenError::InvalidIssuer => TokenHelperStatus::InvalidIssuer,
+        TokenError::InvalidAudience => TokenHelperStatus::InvalidAudience,
+        TokenError::Expired | TokenError::TooOld => TokenHelperStatus::Expired,
+        TokenError::IssuedInFuture | TokenError::NotYetValid => TokenHelperStatus::NotYetValid,
+        // there is no revocation check without a revocation store
+        TokenError::Revoked | TokenError::RevocationCheck(_) => TokenHelperStatus::InvalidToken,
+        TokenError::InvalidSubject(_) => TokenHelperStatus::InvalidSubject,
+    }
+}
+
+/// reads a nul terminated UTF-8 string
+unsafe fn read_str<'a>(value: *const c_char) -> Option<&'a str> {
+    if value.is_null() {
+        return None;
+    }
+    CStr::from_ptr(value).to_str().ok()
+}
+
+fn invalid_argument(message: &str) -> TokenHelperStatus {
+    set_last_error(message);
+    TokenHelperStatus::InvalidArgument
+}
+
+fn set_last_error(message: &str) {
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = message);
+}
+
+#[cfg(test)]
+mod tests;
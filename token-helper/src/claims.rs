@@ -0,0 +1,347 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use josekit::{jwt::JwtPayload, Map, Value};
+
+use crate::{error::TokenError, token_helper::missing_claim, user::UserData};
+
+/// the claims set by the token helper itself, they can not be used as custom claims
+pub const REGISTERED_CLAIMS: [&str; 7] = ["iss", "sub", "aud", "exp", "nbf", "iat", "jti"];
+
+/// the claims written from the fields of `CustomClaims`
+const KNOWN_CLAIMS: [&str; 8] = [
+    "roles",
+    "scope",
+    "email",
+    "email_verified",
+    "auth_time",
+    "amr",
+    "cnf",
+    "act",
+];
+
+/// the key a token is bound to (`cnf` claim, RFC 7800),
+/// the token can only be used by the holder of the key
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Confirmation {
+    // SHA-256 thumbprint of the DPoP key (RFC 9449)
+    pub jkt: Option<String>,
+    // SHA-256 thumbprint of the client certificate (`x5t#S256`, RFC 8705)
+    pub x5t_s256: Option<String>,
+}
+
+impl Confirmation {
+    fn is_empty(&self) -> bool {
+        self.jkt.is_none() && self.x5t_s256.is_none()
+    }
+
+    fn to_value(&self) -> Value {
+        let mut cnf = Map::new();
+        if let Some(jkt) = &self.jkt {
+            cnf.insert("jkt".to_string(), Value::String(jkt.clone()));
+        }
+        if let Some(x5t_s256) = &self.x5t_s256 {
+            cnf.insert("x5t#S256".to_string(), Value::String(x5t_s256.clone()));
+        }
+        Value::Object(cnf)
+    }
+
+    /// reads the `cnf` claim of the payload, empty when the token is not bound
+    pub(crate) fn from_payload(payload: &JwtPayload) -> Result<Self, TokenError> {
+        payload
+            .claim("cnf")
+            .map(Self::from_value)
+            .unwrap_or_else(|| Ok(Self::default()))
+    }
+
+    fn from_value(value: &Value) -> Result<Self, TokenError> {
+        let cnf = value.as_object().ok_or_else(|| invalid_claim("cnf"))?;
+        let member = |name: &str| {
+            cnf.get(name)
+                .map(|v| read_string("cnf", v).map(|s| s.to_string()))
+                .transpose()
+        };
+        Ok(Self {
+            jkt: member("jkt")?,
+            x5t_s256: member("x5t#S256")?,
+        })
+    }
+}
+
+/// the party acting on behalf of the subject of a token (`act` claim, RFC 8693),
+/// `act` is the party that delegated to this actor before, the current actor comes first
+///
+/// # example
+/// ```
+/// use token_helper::claims::Actor;
+///
+/// // the orders service calls the billing service on behalf of the user
+/// // after the frontend called the orders service
+/// let actor = Actor::new("frontend").delegate_to("orders");
+/// assert_eq!(actor.sub, "orders");
+/// assert_eq!(actor.chain(), vec!["orders", "frontend"]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Actor {
+    pub sub: String,
+    pub act: Option<Box<Actor>>,
+}
+
+impl Actor {
+    pub fn new(sub: &str) -> Self {
+        Self {
+            sub: sub.to_string(),
+            act: None,
+        }
+    }
+
+    /// returns a new actor acting on behalf of this chain
+    pub fn delegate_to(self, sub: &str) -> Self {
+        Self {
+            sub: sub.to_string(),
+            act: Some(Box::new(self)),
+        }
+    }
+
+    /// the subjects of the delegation chain, the current actor first
+    pub fn chain(&self) -> Vec<&str> {
+        let mut chain = vec![self.sub.as_str()];
+        let mut actor = &self.act;
+        while let Some(previous) = actor {
+            chain.push(previous.sub.as_str());
+            actor = &previous.act;
+        }
+        chain
+    }
+
+    fn to_value(&self) -> Value {
+        let mut act = Map::new();
+        act.insert("sub".to_string(), Value::String(self.sub.clone()));
+        if let Some(previous) = &self.act {
+            act.insert("act".to_string(), previous.to_value());
+        }
+        Value::Object(act)
+    }
+
+    fn from_value(value: &Value) -> Result<Self, TokenError> {
+        let act = value.as_object().ok_or_else(|| invalid_claim("act"))?;
+        let sub = act
+            .get("sub")
+            .ok_or_else(|| invalid_claim("act"))
+            .and_then(|sub| read_string("act", sub))?;
+        Ok(Self {
+            sub: sub.to_string(),
+            act: act
+                .get("act")
+                .map(|previous| Self::from_value(previous).map(Box::new))
+                .transpose()?,
+        })
+    }
+}
+
+/// the claims of a token that are not related to its validity
+/// (authorization and authentication details)
+///
+/// the scopes are written as a space separated `scope` claim (RFC 8693),
+/// any other claim can be added with `set_claim` as long as it is not reserved
+///
+/// # example
+/// ```
+/// use token_helper::claims::CustomClaims;
+///
+/// let mut claims = CustomClaims::new();
+/// claims.roles = vec!["admin".to_string()];
+/// claims.scopes = vec!["read".to_string(), "write".to_string()];
+/// claims.set_claim("tenant", "acme".into()).unwrap();
+/// // the registered claims are protected
+/// assert!(claims.set_claim("sub", "someone-else".into()).is_err());
+/// assert!(claims.set_claim("roles", "admin".into()).is_err());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CustomClaims {
+    pub roles: Vec<String>,
+    pub scopes: Vec<String>,
+    pub email: Option<String>,
+    pub email_verified: bool,
+    // when the user authenticated, kept when the token is refreshed
+    pub auth_time: Option<SystemTime>,
+    // authentication methods references (RFC 8176) such as `pwd` or `otp`
+    pub amr: Vec<String>,
+    // the key the token is bound to
+    pub cnf: Confirmation,
+    // the delegation chain of an exchanged token
+    pub act: Option<Actor>,
+    extra: Map<String, Value>,
+}
+
+impl CustomClaims {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// sets an additional claim, the registered and the known claims are reserved
+    pub fn set_claim(&mut self, name: &str, value: Value) -> Result<(), TokenError> {
+        if is_reserved(name) {
+            return Err(TokenError::ReservedClaim(name.to_string()));
+        }
+        self.extra.insert(name.to_string(), value);
+        Ok(())
+    }
+
+    pub fn claim(&self, name: &str) -> Option<&Value> {
+        self.extra.get(name)
+    }
+
+    /// returns the additional claims
+    pub fn extra_claims(&self) -> &Map<String, Value> {
+        &self.extra
+    }
+
+    /// returns the claims as they are written in a token (json object)
+    pub fn to_json(&self) -> Result<Value, TokenError> {
+        let mut payload = JwtPayload::new();
+        self.write_to(&mut payload)?;
+        Ok(Value::Object(payload.claims_set().clone()))
+    }
+
+    /// writes the claims into the payload of a token
+    pub(crate) fn write_to(&self, payload: &mut JwtPayload) -> Result<(), TokenError> {
+        let mut set = |name: &str, value: Value| {
+            payload
+                .set_claim(name, Some(value))
+                .map_err(|e| TokenError::Encoding(e.to_string()))
+        };
+        if !self.roles.is_empty() {
+            set("roles", string_array(&self.roles))?;
+        }
+        if !self.scopes.is_empty() {
+            set("scope", Value::String(self.scopes.join(" ")))?;
+        }
+        if let Some(email) = &self.email {
+            set("email", Value::String(email.clone()))?;
+            set("email_verified", Value::Bool(self.email_verified))?;
+        }
+        if let Some(auth_time) = self.auth_time {
+            let seconds = auth_time
+                .duration_since(UNIX_EPOCH)
+                .map_err(|e| TokenError::Encoding(e.to_string()))?
+                .as_secs();
+            set("auth_time", Value::from(seconds))?;
+        }
+        if !self.amr.is_empty() {
+            set("amr", string_array(&self.amr))?;
+        }
+        if !self.cnf.is_empty() {
+            set("cnf", self.cnf.to_value())?;
+        }
+        if let Some(act) = &self.act {
+            set("act", act.to_value())?;
+        }
+        for (name, value) in self.extra.iter() {
+            set(name, value.clone())?;
+        }
+        Ok(())
+    }
+
+    /// reads the claims from the payload of a token,
+    /// the claims with an unexpected type are rejected
+    pub(crate) fn from_payload(payload: &JwtPayload) -> Result<Self, TokenError> {
+        let mut claims = Self::new();
+        for (name, value) in payload.claims_set().iter() {
+            match name.as_str() {
+                "roles" => claims.roles = read_string_array(name, value)?,
+                "scope" => {
+                    claims.scopes = read_string(name, value)?
+                        .split_whitespace()
+                        .map(|s| s.to_string())
+                        .collect()
+                }
+                "email" => claims.email = Some(read_string(name, value)?.to_string()),
+                "email_verified" => {
+                    claims.email_verified = value.as_bool().ok_or_else(|| invalid_claim(name))?
+                }
+                "auth_time" => {
+                    let seconds = value.as_u64().ok_or_else(|| invalid_claim(name))?;
+                    claims.auth_time = Some(UNIX_EPOCH + Duration::from_secs(seconds));
+                }
+                "amr" => claims.amr = read_string_array(name, value)?,
+                "cnf" => claims.cnf = Confirmation::from_value(value)?,
+                "act" => claims.act = Some(Actor::from_value(value)?),
+                name if REGISTERED_CLAIMS.contains(&name) => {}
+                name => {
+                    claims.extra.insert(name.to_string(), value.clone());
+                }
+            }
+        }
+        Ok(claims)
+    }
+}
+
+fn is_reserved(name: &str) -> bool {
+    REGISTERED_CLAIMS.contains(&name) || KNOWN_CLAIMS.contains(&name)
+}
+
+fn invalid_claim(name: &str) -> TokenError {
+    TokenError::InvalidClaim(name.to_string())
+}
+
+fn string_array(values: &[String]) -> Value {
+    Value::Array(values.iter().map(|v| Value::String(v.clone())).collect())
+}
+
+fn read_string<'a>(name: &str, value: &'a Value) -> Result<&'a str, TokenError> {
+    value.as_str().ok_or_else(|| invalid_claim(name))
+}
+
+fn read_string_array(name: &str, value: &Value) -> Result<Vec<String>, TokenError> {
+    value
+        .as_array()
+        .ok_or_else(|| invalid_claim(name))?
+        .iter()
+        .map(|v| read_string(name, v).map(|s| s.to_string()))
+        .collect()
+}
+
+/// the registered claims of a validated token
+#[derive(Debug, PartialEq)]
+pub struct TokenClaims {
+    /// the user of the token, resolved when the subject is a pairwise subject
+    pub user_data: UserData,
+    /// the subject as written in the token
+    pub subject: String,
+    pub issuer: String,
+    pub audiences: Vec<String>,
+    pub jwt_id: Option<String>,
+    pub issued_at: SystemTime,
+    pub expires_at: SystemTime,
+    pub not_before: SystemTime,
+    pub custom: CustomClaims,
+}
+
+impl TokenClaims {
+    /// reads the claims from an already validated payload,
+    /// with the subject returned by the validation
+    pub(crate) fn from_payload(payload: &JwtPayload, subject: &str) -> Result<Self, TokenError> {
+        Ok(Self {
+            user_data: UserData::from_subject(subject).map_err(TokenError::InvalidSubject)?,
+            subject: payload
+                .subject()
+                .ok_or_else(|| missing_claim("sub"))?
+                .to_string(),
+            issuer: payload
+                .issuer()
+                .ok_or_else(|| missing_claim("iss"))?
+                .to_string(),
+            audiences: payload
+                .audience()
+                .ok_or_else(|| missing_claim("aud"))?
+                .iter()
+                .map(|a| a.to_string())
+                .collect(),
+            jwt_id: payload.jwt_id().map(|id| id.to_string()),
+            issued_at: payload.issued_at().ok_or_else(|| missing_claim("iat"))?,
+            expires_at: payload.expires_at().ok_or_else(|| missing_claim("exp"))?,
+            not_before: payload.not_before().ok_or_else(|| missing_claim("nbf"))?,
+            custom: CustomClaims::from_payload(payload)?,
+        })
+    }
+}
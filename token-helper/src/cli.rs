@@ -0,0 +1,383 @@
+use std::time::Duration;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use josekit::{
+    jwk::{
+        alg::{
+            ec::{EcCurve, EcKeyPair},
+            ecx::{EcxCurve, EcxKeyPair},
+            ed::{EdCurve, EdKeyPair},
+        },
+        KeyPair,
+    },
+    jwt::JwtPayload,
+};
+use serde_json::{json, Value};
+
+use crate::{
+    claims::TokenClaims,
+    error::TokenError,
+    key_provider::{KeyProvider, PemFile},
+    manager::{encode_token, EncodingKey, TokenOptions},
+    mtls::certificate_thumbprint_from_pem,
+    token_helper::{
+        decrypt_jwt, validate_header, validate_payload_with_options, validate_signed_header,
+        verify_jwt, ContentEncryption, EncryptionPolicy, EncryptionSuite, KeyManagementAlgorithm,
+        SigningAlgorithm,
+    },
+    user::UserData,
+    validation::ValidationOptions,
+};
+
+/// the help of the command line tool
+pub const USAGE: &str = "usage: token-helper <command> [options]
+
+commands:
+  keygen --type <x25519|p256|ed25519> [--out <prefix>]
+  encode --user-id <id> --realm <realm> --issuer <issuer> --audience <audience>...
+         (--public-key <pem> [--algorithm <alg>] [--encryption <enc>]
+          | --signing-key <pem> [--algorithm <EdDSA|ES256>])
+         [--lifetime <seconds>]
+  decode --private-key <pem> --issuer <issuer> --audience <audience>... [--leeway <seconds>]
+         [--certificate <pem>] [--dpop-thumbprint <jkt>] [<token>]
+  verify --public-key <pem> [--algorithm <EdDSA|ES256>] --issuer <issuer> --audience <audience>...
+         [--leeway <seconds>] [--certificate <pem>] [--dpop-thumbprint <jkt>] [<token>]
+  inspect [--private-key <pem>] [<token>]
+
+the token is read from the standard input when it is not given,
+the output is a json document";
+
+/// the type of the keys created by `keygen`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyType {
+    /// X25519 key for ECDH-ES encryption
+    X25519,
+    /// P-256 key for ECDH-ES encryption or ES256 signatures
+    P256,
+    /// Ed25519 key for EdDSA signatures
+    Ed25519,
+}
+
+impl KeyType {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "x25519" => Some(KeyType::X25519),
+            "p256" | "p-256" => Some(KeyType::P256),
+            "ed25519" => Some(KeyType::Ed25519),
+            _ => None,
+        }
+    }
+}
+
+/// the options and the positional arguments of a command
+struct Args<'a> {
+    options: Vec<(&'a str, &'a str)>,
+    positional: Vec<&'a str>,
+}
+
+impl<'a> Args<'a> {
+    fn parse(args: &'a [String]) -> Result<Self, String> {
+        let mut options = Vec::new();
+        let mut positional = Vec::new();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.strip_prefix("--") {
+                Some(name) => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| format!("no value for --{}", name))?;
+                    options.push((name, value.as_str()));
+                }
+                None => positional.push(arg.as_str()),
+            }
+        }
+        Ok(Self {
+            options,
+            positional,
+        })
+    }
+
+    fn value(&self, name: &str) -> Option<&'a str> {
+        self.options
+            .iter()
+            .rev()
+            .find(|(n, _)| *n == name)
+            .map(|(_, v)| *v)
+    }
+
+    fn values(&self, name: &str) -> Vec<&'a str> {
+        self.options
+            .iter()
+            .filter(|(n, _)| *n == name)
+            .map(|(_, v)| *v)
+            .collect()
+    }
+
+    fn required(&self, name: &str) -> Result<&'a str, String> {
+        self.value(name)
+            .ok_or_else(|| format!("--{} is required", name))
+    }
+
+    fn seconds(&self, name: &str) -> Result<Option<Duration>, String> {
+        self.value(name)
+            .map(|v| {
+                v.parse()
+                    .map(Duration::from_secs)
+                    .map_err(|_| format!("--{} must be a number of seconds", name))
+            })
+            .transpose()
+    }
+
+    fn key_file(&self, name: &str) -> Result<Option<Vec<u8>>, String> {
+        self.value(name)
+            .map(|path| PemFile::new(path).load_key().map_err(|e| e.to_string()))
+            .transpose()
+    }
+
+    /// the token given as argument or read from the standard input
+    fn token(&self) -> Result<String, String> {
+        match self.positional.first() {
+            Some(token) if *token != "-" => Ok(token.to_string()),
+            _ => {
+                let mut token = String::new();
+                std::io::stdin()
+                    .read_line(&mut token)
+                    .map_err(|e| e.to_string())?;
+                Ok(token.trim().to_string())
+            }
+        }
+    }
+
+    fn validation_options(&self) -> Result<ValidationOptions, String> {
+        let audiences = self.values("audience");
+        if audiences.is_empty() {
+            return Err("--audience is required".to_string());
+        }
+        let mut options = ValidationOptions::new(self.required("issuer")?, &audiences);
+        if let Some(leeway) = self.seconds("leeway")? {
+            options = options.with_leeway(leeway);
+        }
+        // the token must be bound to the DPoP key
+        if let Some(jkt) = self.value("dpop-thumbprint") {
+            options = options.with_dpop_thumbprint(jkt);
+        }
+        // the token must be bound to the client certificate
+        if let Some(certificate) = self.key_file("certificate")? {
+            let x5t_s256 =
+                certificate_thumbprint_from_pem(&certificate).map_err(|e| e.to_string())?;
+            options = options.with_certificate_thumbprint(&x5t_s256);
+        }
+        Ok(options)
+    }
+}
+
+/// runs a command of the command line tool and returns its json output
+pub fn run(args: &[String]) -> Result<Value, String> {
+    let (command, args) = args
+        .split_first()
+        .ok_or_else(|| "no command, see token-helper help".to_string())?;
+    let args = Args::parse(args)?;
+    match command.as_str() {
+        "keygen" => {
+            let name = args.required("type")?;
+            let key_type =
+                KeyType::from_name(name).ok_or_else(|| format!("unsupported key type {}", name))?;
+            let (private_key, public_key) = generate_key_pair(key_type)?;
+            if let Some(prefix) = args.value("out") {
+                write_file(&format!("{}.pem", prefix), &private_key)?;
+                write_file(&format!("{}.pub.pem", prefix), &public_key)?;
+            }
+            Ok(json!({
+                "type": name,
+                "private_key": private_key,
+                "public_key": public_key,
+            }))
+        }
+        "encode" => {
+            let user_data = UserData::new(
+                args.required("user-id")?.to_string(),
+                args.required("realm")?.to_string(),
+            )?;
+            let issuer = args.required("issuer")?;
+            let audiences: Vec<String> = args
+                .values("audience")
+                .iter()
+                .map(|a| a.to_string())
+                .collect();
+            if audiences.is_empty() {
+                return Err("--audience is required".to_string());
+            }
+            let mut options = TokenOptions::new(issuer, audiences);
+            if let Some(lifetime) = args.seconds("lifetime")? {
+                options = options.with_lifetime(lifetime);
+            }
+            let key = match (args.key_file("public-key")?, args.key_file("signing-key")?) {
+                (Some(public_key), None) => {
+                    let mut suite = EncryptionSuite::default();
+                    if let Some(name) = args.value("algorithm") {
+                        suite.key_management = KeyManagementAlgorithm::from_name(name)
+                            .ok_or_else(|| format!("unsupported algorithm {}", name))?;
+                    }
+                    if let Some(name) = args.value("encryption") {
+                        suite.content_encryption = ContentEncryption::from_name(name)
+                            .ok_or_else(|| format!("unsupported encryption {}", name))?;
+                    }
+                    EncodingKey::Encrypt { public_key, suite }
+                }
+                (None, Some(private_key)) => EncodingKey::Sign {
+                    private_key,
+                    algorithm: signing_algorithm(args.value("algorithm"))?,
+                },
+                _ => return Err("either --public-key or --signing-key is required".to_string()),
+            };
+            let token = encode_token(&key, &user_data, &options).map_err(|e| e.to_string())?;
+            Ok(json!({ "token": token }))
+        }
+        "decode" => {
+            let private_key = args
+                .key_file("private-key")?
+                .ok_or("--private-key is required")?;
+            let options = args.validation_options()?;
+            Ok(validation_result(decode_token(
+                private_key,
+                &args.token()?,
+                &options,
+            )))
+        }
+        "verify" => {
+            let public_key = args
+                .key_file("public-key")?
+                .ok_or("--public-key is required")?;
+            let algorithm = signing_algorithm(args.value("algorithm"))?;
+            let options = args.validation_options()?;
+            Ok(validation_result(verify_token(
+                public_key,
+                algorithm,
+                &args.token()?,
+                &options,
+            )))
+        }
+        "inspect" => inspect_token(&args.token()?, args.key_file("private-key")?.as_ref()),
+        other => Err(format!("unknown command {}, see token-helper help", other)),
+    }
+}
+
+fn signing_algorithm(name: Option<&str>) -> Result<SigningAlgorithm, String> {
+    match name {
+        Some(name) => SigningAlgorithm::from_name(name)
+            .ok_or_else(|| format!("unsupported algorithm {}", name)),
+        None => Ok(SigningAlgorithm::EdDSA),
+    }
+}
+
+fn write_file(path: &str, content: &str) -> Result<(), String> {
+    std::fs::write(path, content).map_err(|e| format!("could not write {}: {}", path, e))
+}
+
+/// creates a new key pair in PEM format (private key, public key)
+pub fn generate_key_pair(key_type: KeyType) -> Result<(String, String), String> {
+    let (private_key, public_key) = match key_type {
+        KeyType::X25519 => {
+            let key_pair = EcxKeyPair::generate(EcxCurve::X25519).map_err(|e| e.to_string())?;
+            (key_pair.to_pem_private_key(), key_pair.to_pem_public_key())
+        }
+        KeyType::P256 => {
+            let key_pair = EcKeyPair::generate(EcCurve::P256).map_err(|e| e.to_string())?;
+            (key_pair.to_pem_private_key(), key_pair.to_pem_public_key())
+        }
+        KeyType::Ed25519 => {
+            let key_pair = EdKeyPair::generate(EdCurve::Ed25519).map_err(|e| e.to_string())?;
+            (key_pair.to_pem_private_key(), key_pair.to_pem_public_key())
+        }
+    };
+    let pem = |key: Vec<u8>| String::from_utf8(key).map_err(|e| e.to_string());
+    Ok((pem(private_key)?, pem(public_key)?))
+}
+
+/// every algorithm supported by the token helper is accepted by the tool,
+/// the algorithm must still match the key
+fn any_encryption_policy() -> EncryptionPolicy {
+    EncryptionPolicy::new(
+        &[
+            KeyManagementAlgorithm::EcdhEs,
+            KeyManagementAlgorithm::EcdhEsA256kw,
+            KeyManagementAlgorithm::RsaOaep256,
+        ],
+        &[ContentEncryption::A128CbcHs256, ContentEncryption::A256Gcm],
+    )
+}
+
+/// decrypts and validates an encrypted token, returns its claims and payload
+pub fn decode_token(
+    private_key: Vec<u8>,
+    token: &str,
+    options: &ValidationOptions,
+) -> Result<(TokenClaims, JwtPayload), TokenError> {
+    let policy = any_encryption_policy();
+    let (payload, header) = decrypt_jwt(&private_key, token, &policy)?;
+    validate_header(&header, &policy)?;
+    let subject = validate_payload_with_options(&payload, options, None)?;
+    Ok((TokenClaims::from_payload(&payload, &subject)?, payload))
+}
+
+/// verifies and validates a signed token, returns its claims and payload
+pub fn verify_token(
+    public_key: Vec<u8>,
+    algorithm: SigningAlgorithm,
+    token: &str,
+    options: &ValidationOptions,
+) -> Result<(TokenClaims, JwtPayload), TokenError> {
+    let (payload, header) = verify_jwt(&public_key, algorithm, token)?;
+    validate_signed_header(&header, algorithm)?;
+    let subject = validate_payload_with_options(&payload, options, None)?;
+    Ok((TokenClaims::from_payload(&payload, &subject)?, payload))
+}
+
+/// an invalid token is not an error of the tool, it is reported in the output
+fn validation_result(result: Result<(TokenClaims, JwtPayload), TokenError>) -> Value {
+    match result {
+        Ok((claims, payload)) => json!({
+            "valid": true,
+            "user_id": claims.user_data.user_id,
+            "realm": claims.user_data.realm,
+            "payload": Value::Object(payload.claims_set().clone()),
+        }),
+        Err(e) => json!({
+            "valid": false,
+            "error": e.to_string(),
+        }),
+    }
+}
+
+/// prints the header and the payload of a token without validating it,
+/// the payload of an encrypted token is only shown when the private key is given
+/// and the signature of a signed token is not verified
+pub fn inspect_token(token: &str, private_key: Option<&Vec<u8>>) -> Result<Value, String> {
+    let segments: Vec<&str> = token.split('.').collect();
+    let header = decode_segment(segments[0])?;
+    let payload = match segments.len() {
+        // signed token
+        3 => decode_segment(segments[1])?,
+        // encrypted token
+        5 => match private_key {
+            Some(private_key) => {
+                let (payload, _) = decrypt_jwt(private_key, token, &any_encryption_policy())
+                    .map_err(|e| e.to_string())?;
+                Value::Object(payload.claims_set().clone())
+            }
+            None => Value::Null,
+        },
+        _ => return Err("the token is not a json web token".to_string()),
+    };
+    Ok(json!({
+        "header": header,
+        "payload": payload,
+    }))
+}
+
+fn decode_segment(segment: &str) -> Result<Value, String> {
+    let json = URL_SAFE_NO_PAD
+        .decode(segment)
+        .map_err(|e| format!("invalid token segment: {}", e))?;
+    serde_json::from_slice(&json).map_err(|e| format!("invalid token segment: {}", e))
+}
@@ -0,0 +1,218 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use josekit::{
+    jwk::Jwk,
+    jws::{EdDSA, JwsVerifier, ES256},
+    jwt,
+};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    error::TokenError,
+    token_helper::missing_claim,
+    validation::{Clock, SystemClock},
+};
+
+/// the `typ` header of the DPoP proofs
+const DPOP_TYPE: &str = "dpop+jwt";
+/// default time a proof is accepted after its `iat`
+const DEFAULT_PROOF_MAX_AGE: u64 = 60;
+
+/// remembers the ids (`jti`) of the proofs already used
+pub trait ReplayCache: Send + Sync {
+    /// records the proof id until it expires,
+    /// returns false if the proof was already used
+    fn insert(&self, jti: &str, expires_at: SystemTime) -> Result<bool, String>;
+}
+
+/// a replay cache kept in memory, only valid for a single instance of the server
+#[derive(Default)]
+pub struct MemoryReplayCache {
+    seen: Mutex<HashMap<String, SystemTime>>,
+}
+
+impl MemoryReplayCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ReplayCache for MemoryReplayCache {
+    fn insert(&self, jti: &str, expires_at: SystemTime) -> Result<bool, String> {
+        let mut seen = self.seen.lock().map_err(|e| e.to_string())?;
+        let now = SystemTime::now();
+        seen.retain(|_, expires_at| *expires_at > now);
+        Ok(seen.insert(jti.to_string(), expires_at).is_none())
+    }
+}
+
+/// a verified DPoP proof
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DpopProof {
+    /// the SHA-256 thumbprint of the public key of the proof (`cnf.jkt` of the bound tokens)
+    pub jkt: String,
+    pub jti: String,
+    pub issued_at: SystemTime,
+}
+
+/// verifies the DPoP proofs (RFC 9449) sent with the requests
+///
+/// the proof must be a `dpop+jwt` signed with the key of its `jwk` header,
+/// for the method (`htm`) and the uri (`htu`) of the request, recently issued (`iat`)
+/// and not used before (`jti`)
+pub struct DpopVerifier {
+    max_age: Duration,
+    leeway: Duration,
+    clock: Arc<dyn Clock>,
+    replay_cache: Arc<dyn ReplayCache>,
+}
+
+impl DpopVerifier {
+    pub fn new(replay_cache: Arc<dyn ReplayCache>) -> Self {
+        Self {
+            max_age: Duration::from_secs(DEFAULT_PROOF_MAX_AGE),
+            leeway: Duration::ZERO,
+            clock: Arc::new(SystemClock),
+            replay_cache,
+        }
+    }
+
+    /// how long a proof is accepted after it was issued
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    /// tolerance applied to the `iat` claim (clock skew of the clients)
+    pub fn with_leeway(mut self, leeway: Duration) -> Self {
+        self.leeway = leeway;
+        self
+    }
+
+    /// replaces the system clock
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// verifies the proof sent with a request,
+    /// when an access token is given the proof must be bound to it (`ath` claim)
+    pub fn verify(
+        &self,
+        proof: &str,
+        method: &str,
+        uri: &str,
+        access_token: Option<&str>,
+    ) -> Result<DpopProof, TokenError> {
+        let header = jwt::decode_header(proof).map_err(invalid_proof)?;
+        if header.claim("typ").and_then(|t| t.as_str()) != Some(DPOP_TYPE) {
+            return Err(invalid_proof("the type must be dpop+jwt"));
+        }
+        let jwk = match header.claim("jwk") {
+            Some(josekit::Value::Object(map)) => {
+                Jwk::from_map(map.clone()).map_err(invalid_proof)?
+            }
+            _ => return Err(invalid_proof("no jwk header")),
+        };
+        if jwk.parameter("d").is_some() {
+            return Err(invalid_proof("the jwk header is a private key"));
+        }
+        let verifier: Box<dyn JwsVerifier> = match header.claim("alg").and_then(|a| a.as_str()) {
+            Some("EdDSA") => Box::new(EdDSA.verifier_from_jwk(&jwk).map_err(invalid_proof)?),
+            Some("ES256") => Box::new(ES256.verifier_from_jwk(&jwk).map_err(invalid_proof)?),
+            _ => return Err(invalid_proof("unsupported algorithm")),
+        };
+        let (payload, _) =
+            jwt::decode_with_verifier(proof, verifier.as_ref()).map_err(invalid_proof)?;
+
+        let jti = payload.jwt_id().ok_or_else(|| missing_claim("jti"))?;
+        let htm = payload.claim("htm").and_then(|v| v.as_str());
+        let htu = payload.claim("htu").and_then(|v| v.as_str());
+        let issued_at = payload.issued_at().ok_or_else(|| missing_claim("iat"))?;
+        if htm != Some(method) {
+            return Err(invalid_proof("the htm claim does not match the request"));
+        }
+        if htu.map(without_query) != Some(without_query(uri)) {
+            return Err(invalid_proof("the htu claim does not match the request"));
+        }
+        let now = self.clock.now();
+        if issued_at > now + self.leeway {
+            return Err(invalid_proof("the proof is issued in the future"));
+        }
+        let expires_at = issued_at + self.max_age + self.leeway;
+        if expires_at < now {
+            return Err(invalid_proof("the proof is too old"));
+        }
+        if let Some(access_token) = access_token {
+            let ath = URL_SAFE_NO_PAD.encode(Sha256::digest(access_token.as_bytes()));
+            if payload.claim("ath").and_then(|v| v.as_str()) != Some(ath.as_str()) {
+                return Err(invalid_proof(
+                    "the ath claim does not match the access token",
+                ));
+            }
+        }
+        if !self
+            .replay_cache
+            .insert(jti, expires_at)
+            .map_err(|e| invalid_proof(format!("replay check failed: {}", e)))?
+        {
+            return Err(invalid_proof("the proof was already used"));
+        }
+        Ok(DpopProof {
+            jkt: jwk_thumbprint(&jwk)?,
+            jti: jti.to_string(),
+            issued_at,
+        })
+    }
+}
+
+/// returns the SHA-256 thumbprint of a public key (RFC 7638), base64url encoded
+///
+/// # example
+/// ```
+/// use josekit::jwk::Jwk;
+/// use token_helper::dpop::jwk_thumbprint;
+///
+/// // the example of RFC 8037 appendix A.3
+/// let jwk = Jwk::from_bytes(br#"{"kty":"OKP","crv":"Ed25519",
+///     "x":"11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHURo"}"#).unwrap();
+/// assert_eq!(jwk_thumbprint(&jwk).unwrap(), "kPrK_qmxVWaYVA9wwBF6Iuo3vVzz7TxHCTwXBygrS4k");
+/// ```
+pub fn jwk_thumbprint(jwk: &Jwk) -> Result<String, TokenError> {
+    let members: &[&str] = match jwk.key_type() {
+        "OKP" => &["crv", "kty", "x"],
+        "EC" => &["crv", "kty", "x", "y"],
+        "RSA" => &["e", "kty", "n"],
+        other => {
+            return Err(TokenError::InvalidKey(format!(
+                "unsupported key type {}",
+                other
+            )))
+        }
+    };
+    let mut required = BTreeMap::new();
+    for member in members {
+        let value = jwk
+            .parameter(member)
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| TokenError::InvalidKey(format!("no {} member", member)))?;
+        required.insert(*member, value);
+    }
+    let json =
+        serde_json::to_string(&required).map_err(|e| TokenError::InvalidKey(e.to_string()))?;
+    Ok(URL_SAFE_NO_PAD.encode(Sha256::digest(json.as_bytes())))
+}
+
+/// the `htu` is compared without the query and the fragment
+fn without_query(uri: &str) -> &str {
+    uri.split(['?', '#']).next().unwrap_or(uri)
+}
+
+fn invalid_proof(e: impl ToString) -> TokenError {
+    TokenError::InvalidProof(e.to_string())
+}
@@ -0,0 +1,77 @@
+use std::fmt;
+
+/// the errors returned when a token can not be issued or is not valid
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenError {
+    /// the key could not be loaded or can not be used for the operation
+    InvalidKey(String),
+    /// the `kid` header names a key that is not in the key ring
+    UnknownKey(String),
+    /// the header is missing, can not be read or has an unexpected value
+    MalformedHeader(String),
+    /// the token could not be decrypted
+    Decryption(String),
+    /// the signature of the token could not be verified
+    InvalidSignature(String),
+    /// the token could not be created
+    Encoding(String),
+    /// a required claim is missing from the payload
+    MissingClaim(String),
+    /// the payload has a claim that is not accepted
+    ForbiddenClaim(String),
+    /// the value of a claim does not have the expected type
+    InvalidClaim(String),
+    /// the claim is set by the token helper and can not be used as a custom claim
+    ReservedClaim(String),
+    /// the token was issued by someone else
+    InvalidIssuer,
+    /// none of the audiences of the token is accepted
+    InvalidAudience,
+    /// the token was issued in the future
+    IssuedInFuture,
+    /// the token is past its expiration time
+    Expired,
+    /// the token is used before its not before time
+    NotYetValid,
+    /// the token was issued longer ago than the accepted maximum age
+    TooOld,
+    /// the token was revoked before it expired
+    Revoked,
+    /// the revocation check failed, the token is rejected
+    RevocationCheck(String),
+    /// the subject is not a valid user data
+    InvalidSubject(String),
+    /// the proof of possession is invalid or the token is not bound to its key
+    InvalidProof(String),
+}
+
+impl fmt::Display for TokenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenError::InvalidKey(e) => write!(f, "invalid key: {}", e),
+            TokenError::UnknownKey(kid) => write!(f, "unknown key id {}", kid),
+            TokenError::MalformedHeader(e) => write!(f, "malformed header: {}", e),
+            TokenError::Decryption(e) => write!(f, "could not decrypt the token: {}", e),
+            TokenError::InvalidSignature(e) => write!(f, "invalid signature: {}", e),
+            TokenError::Encoding(e) => write!(f, "could not create the token: {}", e),
+            TokenError::MissingClaim(claim) => write!(f, "no {} claim", claim),
+            TokenError::ForbiddenClaim(claim) => write!(f, "the {} claim is not accepted", claim),
+            TokenError::InvalidClaim(claim) => write!(f, "invalid {} claim", claim),
+            TokenError::ReservedClaim(claim) => write!(f, "the {} claim is reserved", claim),
+            TokenError::InvalidIssuer => write!(f, "invalid issuer"),
+            TokenError::InvalidAudience => {
+                write!(f, "there is not an valid audience for this token")
+            }
+            TokenError::IssuedInFuture => write!(f, "token issued in the future"),
+            TokenError::Expired => write!(f, "token expired"),
+            TokenError::NotYetValid => write!(f, "token not valid yet"),
+            TokenError::TooOld => write!(f, "token too old"),
+            TokenError::Revoked => write!(f, "token revoked"),
+            TokenError::RevocationCheck(e) => write!(f, "revocation check failed: {}", e),
+            TokenError::InvalidSubject(e) => write!(f, "invalid subject: {}", e),
+            TokenError::InvalidProof(e) => write!(f, "invalid proof of possession: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for TokenError {}
@@ -1,6 +1,18 @@
+pub mod claims;
+pub mod cli;
+pub mod dpop;
+pub mod error;
+pub mod key_provider;
+pub mod key_ring;
+pub mod mtls;
 pub mod token_helper;
 pub mod user;
+pub mod validation;
 pub mod manager;
+pub mod format;
+pub mod pairwise;
+#[cfg(feature = "paseto")]
+pub mod paseto;
 
 #[cfg(test)]
 mod tests;
@@ -0,0 +1,23 @@
+use std::{io::Write, process::ExitCode};
+
+use serde_json::json;
+use token_helper::cli;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(|a| a.as_str()) == Some("help") {
+        let _ = writeln!(std::io::stdout(), "{}", cli::USAGE);
+        return ExitCode::SUCCESS;
+    }
+    let (output, code) = match cli::run(&args) {
+        Ok(output) => (output, ExitCode::SUCCESS),
+        Err(e) => {
+            eprintln!("{}", e);
+            (json!({ "error": e }), ExitCode::FAILURE)
+        }
+    };
+    // the output can be closed early by the reader (head for example)
+    let json = serde_json::to_string_pretty(&output).unwrap_or_default();
+    let _ = writeln!(std::io::stdout(), "{}", json);
+    code
+}
@@ -0,0 +1,36 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use openssl::x509::X509;
+use sha2::{Digest, Sha256};
+
+use crate::error::TokenError;
+
+/// returns the SHA-256 thumbprint of a DER encoded certificate, base64url encoded
+/// (the `x5t#S256` confirmation of the certificate-bound tokens, RFC 8705)
+pub fn certificate_thumbprint(der: &[u8]) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(der))
+}
+
+/// returns the SHA-256 thumbprint of a PEM encoded certificate
+///
+/// # example
+/// ```
+/// use token_helper::mtls::certificate_thumbprint_from_pem;
+///
+/// let certificate = r#"-----BEGIN CERTIFICATE-----
+/// MIIBQjCB9aADAgECAhQIz5AcT0LBvL7WO+RV8WIOPVtnuTAFBgMrZXAwFjEUMBIG
+/// A1UEAwwLdGVzdC1jbGllbnQwIBcNMjYxMDE4MTMwNjUyWhgPMjEyNjA5MjQxMzA2
+/// NTJaMBYxFDASBgNVBAMMC3Rlc3QtY2xpZW50MCowBQYDK2VwAyEAW4225P/NIwIt
+/// v8dZiJ2E5jE2mvK79rtOkMmRhEtWv5CjUzBRMB0GA1UdDgQWBBQAIhHhL3f4WBZj
+/// oyUCjJ9EtUXASTAfBgNVHSMEGDAWgBQAIhHhL3f4WBZjoyUCjJ9EtUXASTAPBgNV
+/// HRMBAf8EBTADAQH/MAUGAytlcANBAEkUYsY65b8Errjh5jWq8lN0TdIuU30wXeeJ
+/// DRXhtSqBpQ+m5QIh0HrZMQmi6NRabL27UOyEtzPEqGgGjjdvKAs=
+/// -----END CERTIFICATE-----"#;
+/// let x5t_s256 = certificate_thumbprint_from_pem(certificate.as_bytes()).unwrap();
+/// assert_eq!(x5t_s256, "fkMnGW17dOr2HBcOS6dJVPwfx8MeWxcY3qddUWKw8TA");
+/// ```
+pub fn certificate_thumbprint_from_pem(pem: &[u8]) -> Result<String, TokenError> {
+    let der = X509::from_pem(pem)
+        .and_then(|certificate| certificate.to_der())
+        .map_err(|_| TokenError::InvalidKey("not a PEM certificate".to_string()))?;
+    Ok(certificate_thumbprint(&der))
+}
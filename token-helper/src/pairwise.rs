@@ -0,0 +1,61 @@
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::{error::TokenError, user::UserData};
+
+/// the minimum length of the secret used to derive the pairwise subjects
+pub const MIN_PAIRWISE_SECRET_LEN: usize = 32;
+
+/// maps a pairwise subject back to the user it was derived from
+/// (implemented by the issuer, which knows the users of each audience)
+pub trait SubjectResolver: Send + Sync {
+    /// returns the user behind the subject of a token issued for the audience,
+    /// or none when the subject is the public subject of the user
+    fn resolve(&self, user_data: &UserData, audience: &str) -> Result<Option<UserData>, String>;
+}
+
+/// derives the pairwise (per audience) subject of an user
+///
+/// the user id is replaced by an HMAC-SHA256 over the subject of the user and the audience,
+/// so the same user gets a stable subject for one application that can not be correlated
+/// with the subject given to another application, the realm is kept
+///
+/// # example
+/// ```
+/// use token_helper::{pairwise::pairwise_user_data, user::UserData};
+///
+/// let secret = b"0123456789abcdef0123456789abcdef";
+/// let user_data = UserData::new(
+///     "1234567890abcdef1234567890abcdef12345678".to_string(),
+///     "test-realm".to_string()).unwrap();
+/// let first = pairwise_user_data(secret, &user_data, "first-app").unwrap();
+/// let second = pairwise_user_data(secret, &user_data, "second-app").unwrap();
+/// assert_ne!(first.user_id, second.user_id);
+/// assert_eq!(first, pairwise_user_data(secret, &user_data, "first-app").unwrap());
+/// assert_eq!(first.realm, "test-realm");
+/// ```
+pub fn pairwise_user_data(
+    secret: &[u8],
+    user_data: &UserData,
+    audience: &str,
+) -> Result<UserData, TokenError> {
+    if secret.len() < MIN_PAIRWISE_SECRET_LEN {
+        return Err(TokenError::InvalidKey(format!(
+            "the pairwise secret must be at least {} bytes long",
+            MIN_PAIRWISE_SECRET_LEN
+        )));
+    }
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret)
+        .map_err(|e| TokenError::InvalidKey(e.to_string()))?;
+    mac.update(user_data.get_subject().as_bytes());
+    // the subject can not contain a null byte, the input is not ambiguous
+    mac.update(&[0]);
+    mac.update(audience.as_bytes());
+    let user_id = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>();
+    UserData::new(user_id, user_data.realm.clone()).map_err(TokenError::InvalidSubject)
+}
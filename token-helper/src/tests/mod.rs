@@ -1,2 +1,12 @@
+mod claims;
+mod cli;
+mod dpop;
+mod key_provider;
+mod key_ring;
+mod mtls;
 mod token_helper;
-mod manager;
\ No newline at end of file
+mod manager;
+mod pairwise;
+#[cfg(feature = "paseto")]
+mod paseto;
+mod validation;
\ No newline at end of file
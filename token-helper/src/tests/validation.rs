@@ -0,0 +1,115 @@
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use josekit::jwt::JwtPayload;
+
+use crate::{
+    error::TokenError,
+    token_helper::{make_payload, validate_payload_with_options},
+    validation::{FixedClock, ValidationOptions},
+};
+
+const SUBJECT: &str = "1234567890abcdef1234567890abcdef12345678:test-realm";
+
+fn payload_issued_at(issued_at: SystemTime) -> JwtPayload {
+    make_payload(
+        "test.localdomain",
+        vec!["test.app".to_string()],
+        &Some(issued_at),
+        &None,
+        Duration::from_secs(3600),
+        SUBJECT,
+    )
+}
+
+fn options_at(now: SystemTime) -> ValidationOptions {
+    ValidationOptions::new("test.localdomain", &["test.app"]).with_clock(Arc::new(FixedClock(now)))
+}
+
+#[test]
+fn test_fixed_clock() {
+    let issued_at = SystemTime::UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+    let payload = payload_issued_at(issued_at);
+    let options = options_at(issued_at + Duration::from_secs(60));
+    assert_eq!(
+        validate_payload_with_options(&payload, &options, None).unwrap(),
+        SUBJECT
+    );
+    let options = options_at(issued_at + Duration::from_secs(3601));
+    assert_eq!(
+        validate_payload_with_options(&payload, &options, None).unwrap_err(),
+        TokenError::Expired
+    );
+}
+
+#[test]
+fn test_leeway() {
+    let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+    // issued by a node with a clock 10 seconds ahead
+    let payload = payload_issued_at(now + Duration::from_secs(10));
+    assert_eq!(
+        validate_payload_with_options(&payload, &options_at(now), None).unwrap_err(),
+        TokenError::IssuedInFuture
+    );
+    let options = options_at(now).with_leeway(Duration::from_secs(30));
+    assert!(validate_payload_with_options(&payload, &options, None).is_ok());
+
+    // expired a few seconds ago
+    let options = options_at(now + Duration::from_secs(3620)).with_leeway(Duration::from_secs(30));
+    assert!(validate_payload_with_options(&payload, &options, None).is_ok());
+    let options = options_at(now + Duration::from_secs(3650)).with_leeway(Duration::from_secs(30));
+    assert_eq!(
+        validate_payload_with_options(&payload, &options, None).unwrap_err(),
+        TokenError::Expired
+    );
+}
+
+#[test]
+fn test_max_age() {
+    let issued_at = SystemTime::UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+    let payload = payload_issued_at(issued_at);
+    let options = options_at(issued_at + Duration::from_secs(600));
+    assert!(validate_payload_with_options(&payload, &options, None).is_ok());
+    let options = options.with_max_age(Duration::from_secs(300));
+    assert_eq!(
+        validate_payload_with_options(&payload, &options, None).unwrap_err(),
+        TokenError::TooOld
+    );
+}
+
+#[test]
+fn test_required_and_forbidden_claims() {
+    let now = SystemTime::now();
+    let mut payload = payload_issued_at(now);
+    let options = options_at(now).require_claim("jti").forbid_claim("act");
+    assert!(validate_payload_with_options(&payload, &options, None).is_ok());
+
+    let options = options.require_claim("scope");
+    assert_eq!(
+        validate_payload_with_options(&payload, &options, None).unwrap_err(),
+        TokenError::MissingClaim("scope".to_string())
+    );
+    payload.set_claim("scope", Some("read".into())).unwrap();
+    assert!(validate_payload_with_options(&payload, &options, None).is_ok());
+
+    payload.set_claim("act", Some("delegated".into())).unwrap();
+    assert_eq!(
+        validate_payload_with_options(&payload, &options, None).unwrap_err(),
+        TokenError::ForbiddenClaim("act".to_string())
+    );
+}
+
+#[test]
+fn test_realm() {
+    let now = SystemTime::now();
+    let payload = payload_issued_at(now);
+    let options = options_at(now).with_realm("test-realm");
+    assert!(validate_payload_with_options(&payload, &options, None).is_ok());
+    let options = options_at(now).with_realm("other-realm");
+    assert!(matches!(
+        validate_payload_with_options(&payload, &options, None),
+        Err(TokenError::InvalidSubject(_))
+    ));
+}
@@ -1,23 +1,445 @@
 use std::time::SystemTime;
 
 use josekit::{
-    jwe::{JweHeader, ECDH_ES},
+    jwe::{self, JweDecrypter, JweEncrypter, JweHeader, ECDH_ES, ECDH_ES_A256KW, RSA_OAEP_256},
+    jwk::Jwk,
+    jws::{EdDSA, JwsHeader, JwsSigner, JwsVerifier, ES256},
     jwt::{self, JwtPayload},
-    JoseError,
+    JoseHeader,
 };
 
+use uuid::Uuid;
+
+use crate::{
+    claims::Confirmation,
+    error::TokenError,
+    key_ring::{KeyPurpose, KeyRing, RingKey},
+    user::UserData,
+    validation::ValidationOptions,
+};
+
+/// a hook to check if a token was revoked before it expired
+/// the tokens are identified by their `jti` claim
+pub trait RevocationCheck {
+    /// returns true if the token with the given id was revoked
+    /// an error rejects the token as well
+    fn is_revoked(&self, jwt_id: &str) -> Result<bool, String>;
+}
+
+/// algorithms supported to sign the json web tokens
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SigningAlgorithm {
+    /// Edwards-curve signature with an Ed25519 key
+    EdDSA,
+    /// ECDSA signature with a P-256 key and SHA-256
+    ES256,
+}
+
+impl SigningAlgorithm {
+    /// returns the name of the algorithm as used in the `alg` header
+    pub fn name(&self) -> &'static str {
+        match self {
+            SigningAlgorithm::EdDSA => "EdDSA",
+            SigningAlgorithm::ES256 => "ES256",
+        }
+    }
+
+    /// returns the algorithm with the given `alg` header name
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "EdDSA" => Some(SigningAlgorithm::EdDSA),
+            "ES256" => Some(SigningAlgorithm::ES256),
+            _ => None,
+        }
+    }
+
+    fn signer_from_pem(&self, private_key: &Vec<u8>) -> Result<Box<dyn JwsSigner>, TokenError> {
+        let signer: Box<dyn JwsSigner> = match self {
+            SigningAlgorithm::EdDSA => {
+                Box::new(EdDSA.signer_from_pem(private_key).map_err(invalid_key)?)
+            }
+            SigningAlgorithm::ES256 => {
+                Box::new(ES256.signer_from_pem(private_key).map_err(invalid_key)?)
+            }
+        };
+        Ok(signer)
+    }
+
+    fn verifier_from_pem(&self, public_key: &Vec<u8>) -> Result<Box<dyn JwsVerifier>, TokenError> {
+        let verifier: Box<dyn JwsVerifier> = match self {
+            SigningAlgorithm::EdDSA => {
+                Box::new(EdDSA.verifier_from_pem(public_key).map_err(invalid_key)?)
+            }
+            SigningAlgorithm::ES256 => {
+                Box::new(ES256.verifier_from_pem(public_key).map_err(invalid_key)?)
+            }
+        };
+        Ok(verifier)
+    }
+
+    pub(crate) fn verifier_from_jwk(
+        &self,
+        public_key: &Jwk,
+    ) -> Result<Box<dyn JwsVerifier>, TokenError> {
+        let verifier: Box<dyn JwsVerifier> = match self {
+            SigningAlgorithm::EdDSA => {
+                Box::new(EdDSA.verifier_from_jwk(public_key).map_err(invalid_key)?)
+            }
+            SigningAlgorithm::ES256 => {
+                Box::new(ES256.verifier_from_jwk(public_key).map_err(invalid_key)?)
+            }
+        };
+        Ok(verifier)
+    }
+}
+
+/// key management algorithms supported to encrypt the json web tokens
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyManagementAlgorithm {
+    /// direct key agreement with an X25519 key
+    EcdhEs,
+    /// key agreement with an X25519 key, the content key is wrapped with AES-256 key wrap
+    EcdhEsA256kw,
+    /// the content key is encrypted with RSAES OAEP using SHA-256
+    RsaOaep256,
+}
+
+impl KeyManagementAlgorithm {
+    /// returns the name of the algorithm as used in the `alg` header
+    pub fn name(&self) -> &'static str {
+        match self {
+            KeyManagementAlgorithm::EcdhEs => "ECDH-ES",
+            KeyManagementAlgorithm::EcdhEsA256kw => "ECDH-ES+A256KW",
+            KeyManagementAlgorithm::RsaOaep256 => "RSA-OAEP-256",
+        }
+    }
+
+    /// returns the algorithm with the given `alg` header name
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "ECDH-ES" => Some(KeyManagementAlgorithm::EcdhEs),
+            "ECDH-ES+A256KW" => Some(KeyManagementAlgorithm::EcdhEsA256kw),
+            "RSA-OAEP-256" => Some(KeyManagementAlgorithm::RsaOaep256),
+            _ => None,
+        }
+    }
+
+    fn encrypter_from_pem(
+        &self,
+        public_key: &Vec<u8>,
+    ) -> Result<Box<dyn JweEncrypter>, TokenError> {
+        let encrypter: Box<dyn JweEncrypter> = match self {
+            KeyManagementAlgorithm::EcdhEs => Box::new(
+                ECDH_ES
+                    .encrypter_from_pem(public_key)
+                    .map_err(invalid_key)?,
+            ),
+            KeyManagementAlgorithm::EcdhEsA256kw => Box::new(
+                ECDH_ES_A256KW
+                    .encrypter_from_pem(public_key)
+                    .map_err(invalid_key)?,
+            ),
+            KeyManagementAlgorithm::RsaOaep256 => Box::new(
+                RSA_OAEP_256
+                    .encrypter_from_pem(public_key)
+                    .map_err(invalid_key)?,
+            ),
+        };
+        Ok(encrypter)
+    }
+
+    pub(crate) fn encrypter_from_jwk(
+        &self,
+        public_jwk: &Jwk,
+    ) -> Result<Box<dyn JweEncrypter>, TokenError> {
+        let encrypter: Box<dyn JweEncrypter> = match self {
+            KeyManagementAlgorithm::EcdhEs => Box::new(
+                ECDH_ES
+                    .encrypter_from_jwk(public_jwk)
+                    .map_err(invalid_key)?,
+            ),
+            KeyManagementAlgorithm::EcdhEsA256kw => Box::new(
+                ECDH_ES_A256KW
+                    .encrypter_from_jwk(public_jwk)
+                    .map_err(invalid_key)?,
+            ),
+            KeyManagementAlgorithm::RsaOaep256 => Box::new(
+                RSA_OAEP_256
+                    .encrypter_from_jwk(public_jwk)
+                    .map_err(invalid_key)?,
+            ),
+        };
+        Ok(encrypter)
+    }
+
+    /// the key id is set on the decrypter when given
+    pub(crate) fn decrypter_from_pem(
+        &self,
+        private_key: &Vec<u8>,
+        kid: Option<&str>,
+    ) -> Result<Box<dyn JweDecrypter>, TokenError> {
+        let decrypter: Box<dyn JweDecrypter> = match self {
+            KeyManagementAlgorithm::EcdhEs => {
+                let mut decrypter = ECDH_ES
+                    .decrypter_from_pem(private_key)
+                    .map_err(invalid_key)?;
+                if let Some(kid) = kid {
+                    decrypter.set_key_id(kid);
+                }
+                Box::new(decrypter)
+            }
+            KeyManagementAlgorithm::EcdhEsA256kw => {
+                let mut decrypter = ECDH_ES_A256KW
+                    .decrypter_from_pem(private_key)
+                    .map_err(invalid_key)?;
+                if let Some(kid) = kid {
+                    decrypter.set_key_id(kid);
+                }
+                Box::new(decrypter)
+            }
+            KeyManagementAlgorithm::RsaOaep256 => {
+                let mut decrypter = RSA_OAEP_256
+                    .decrypter_from_pem(private_key)
+                    .map_err(invalid_key)?;
+                if let Some(kid) = kid {
+                    decrypter.set_key_id(kid);
+                }
+                Box::new(decrypter)
+            }
+        };
+        Ok(decrypter)
+    }
+}
+
+/// content encryption algorithms supported to encrypt the json web tokens
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentEncryption {
+    /// AES-128 in CBC mode with HMAC SHA-256
+    A128CbcHs256,
+    /// AES-256 in GCM mode
+    A256Gcm,
+}
+
+impl ContentEncryption {
+    /// returns the name of the algorithm as used in the `enc` header
+    pub fn name(&self) -> &'static str {
+        match self {
+            ContentEncryption::A128CbcHs256 => "A128CBC-HS256",
+            ContentEncryption::A256Gcm => "A256GCM",
+        }
+    }
+
+    /// returns the algorithm with the given `enc` header name
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "A128CBC-HS256" => Some(ContentEncryption::A128CbcHs256),
+            "A256GCM" => Some(ContentEncryption::A256Gcm),
+            _ => None,
+        }
+    }
+}
+
+/// the algorithms used to encrypt a token,
+/// by default `ECDH-ES` with `A128CBC-HS256`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncryptionSuite {
+    pub key_management: KeyManagementAlgorithm,
+    pub content_encryption: ContentEncryption,
+}
+
+impl Default for EncryptionSuite {
+    fn default() -> Self {
+        Self {
+            key_management: KeyManagementAlgorithm::EcdhEs,
+            content_encryption: ContentEncryption::A128CbcHs256,
+        }
+    }
+}
+
+/// the algorithms accepted in the header of an encrypted token,
+/// by default only `ECDH-ES` with `A128CBC-HS256`
+///
+/// # example
+/// ```
+/// use token_helper::token_helper::{ContentEncryption, EncryptionPolicy, KeyManagementAlgorithm};
+///
+/// let policy = EncryptionPolicy::new(
+///     &[KeyManagementAlgorithm::EcdhEsA256kw, KeyManagementAlgorithm::RsaOaep256],
+///     &[ContentEncryption::A256Gcm],
+/// );
+/// assert!(policy.allows_content_encryption(ContentEncryption::A256Gcm));
+/// assert!(!policy.allows_key_management(KeyManagementAlgorithm::EcdhEs));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncryptionPolicy {
+    key_management: Vec<KeyManagementAlgorithm>,
+    content_encryption: Vec<ContentEncryption>,
+}
+
+impl EncryptionPolicy {
+    pub fn new(
+        key_management: &[KeyManagementAlgorithm],
+        content_encryption: &[ContentEncryption],
+    ) -> Self {
+        Self {
+            key_management: key_management.to_vec(),
+            content_encryption: content_encryption.to_vec(),
+        }
+    }
+
+    pub fn allows_key_management(&self, algorithm: KeyManagementAlgorithm) -> bool {
+        self.key_management.contains(&algorithm)
+    }
+
+    pub fn allows_content_encryption(&self, algorithm: ContentEncryption) -> bool {
+        self.content_encryption.contains(&algorithm)
+    }
+
+    /// returns the key management algorithm of the header if it is accepted
+    pub(crate) fn check_key_management(
+        &self,
+        header: &dyn JoseHeader,
+    ) -> Result<KeyManagementAlgorithm, TokenError> {
+        let alg = header
+            .claim("alg")
+            .ok_or_else(|| malformed_header("no JWT token algorithm"))?;
+        alg.as_str()
+            .and_then(KeyManagementAlgorithm::from_name)
+            .filter(|a| self.allows_key_management(*a))
+            .ok_or_else(|| malformed_header("invalid JWT token algorithm"))
+    }
+
+    /// returns the content encryption of the header if it is accepted
+    pub(crate) fn check_content_encryption(
+        &self,
+        header: &dyn JoseHeader,
+    ) -> Result<ContentEncryption, TokenError> {
+        let enc = header
+            .claim("enc")
+            .ok_or_else(|| malformed_header("no JWT token encryption"))?;
+        enc.as_str()
+            .and_then(ContentEncryption::from_name)
+            .filter(|e| self.allows_content_encryption(*e))
+            .ok_or_else(|| malformed_header("invalid JWT token encryption"))
+    }
+}
+
+impl Default for EncryptionPolicy {
+    fn default() -> Self {
+        let suite = EncryptionSuite::default();
+        Self::new(&[suite.key_management], &[suite.content_encryption])
+    }
+}
+
+pub(crate) fn invalid_key(e: impl ToString) -> TokenError {
+    TokenError::InvalidKey(e.to_string())
+}
+
+fn encoding(e: impl ToString) -> TokenError {
+    TokenError::Encoding(e.to_string())
+}
+
+fn decryption(e: impl ToString) -> TokenError {
+    TokenError::Decryption(e.to_string())
+}
+
+fn invalid_signature(e: impl ToString) -> TokenError {
+    TokenError::InvalidSignature(e.to_string())
+}
+
+fn malformed_header(e: impl ToString) -> TokenError {
+    TokenError::MalformedHeader(e.to_string())
+}
+
+pub(crate) fn missing_claim(claim: &str) -> TokenError {
+    TokenError::MissingClaim(claim.to_string())
+}
+
 /// makes a new encrypted json web token
 /// the token is encrypted with the public key of the user
-pub(crate) fn make_jwt(public_key: &Vec<u8>, payload: &JwtPayload) -> Result<String, JoseError> {
+pub(crate) fn make_jwt(
+    public_key: &Vec<u8>,
+    suite: &EncryptionSuite,
+    payload: &JwtPayload,
+) -> Result<String, TokenError> {
+    let encrypter = suite.key_management.encrypter_from_pem(public_key)?;
+    encrypt_jwt(encrypter.as_ref(), suite.content_encryption, payload)
+}
+
+/// encrypts the payload with the given encrypter
+/// if the encrypter has a key id it is set in the `kid` header
+pub(crate) fn encrypt_jwt(
+    encrypter: &dyn JweEncrypter,
+    content_encryption: ContentEncryption,
+    payload: &JwtPayload,
+) -> Result<String, TokenError> {
     let mut header = JweHeader::new();
     header.set_token_type("JWT");
-    header.set_content_encryption("A128CBC-HS256");
+    header.set_content_encryption(content_encryption.name());
 
-    let encrypter = ECDH_ES.encrypter_from_pem(public_key)?;
-    let jwe = jwt::encode_with_encrypter(payload, &header, &encrypter)?;
+    let jwe = jwt::encode_with_encrypter(payload, &header, encrypter).map_err(encoding)?;
     Ok(jwe)
 }
 
+/// makes a new signed json web token
+/// the token is signed with the private key of the issuer
+pub(crate) fn sign_jwt(
+    private_key: &Vec<u8>,
+    algorithm: SigningAlgorithm,
+    payload: &JwtPayload,
+) -> Result<String, TokenError> {
+    let signer = algorithm.signer_from_pem(private_key)?;
+    sign_jwt_with(signer.as_ref(), payload)
+}
+
+/// signs the payload with the given signer
+/// if the signer has a key id it is set in the `kid` header
+pub(crate) fn sign_jwt_with(
+    signer: &dyn JwsSigner,
+    payload: &JwtPayload,
+) -> Result<String, TokenError> {
+    let mut header = JwsHeader::new();
+    header.set_token_type("JWT");
+
+    let jws = jwt::encode_with_signer(payload, &header, signer).map_err(encoding)?;
+    Ok(jws)
+}
+
+/// makes a new nested json web token
+/// the token is signed with the private key of the issuer
+/// and then encrypted with the public key of the user
+pub(crate) fn make_nested_jwt(
+    signing_key: &Vec<u8>,
+    algorithm: SigningAlgorithm,
+    public_key: &Vec<u8>,
+    suite: &EncryptionSuite,
+    payload: &JwtPayload,
+) -> Result<String, TokenError> {
+    let jws = sign_jwt(signing_key, algorithm, payload)?;
+    let encrypter = suite.key_management.encrypter_from_pem(public_key)?;
+    encrypt_nested_jwt(encrypter.as_ref(), suite.content_encryption, &jws)
+}
+
+/// encrypts a signed jwt with the given encrypter (nested jwt)
+/// if the encrypter has a key id it is set in the `kid` header
+pub(crate) fn encrypt_nested_jwt(
+    encrypter: &dyn JweEncrypter,
+    content_encryption: ContentEncryption,
+    jws: &str,
+) -> Result<String, TokenError> {
+    let mut header = JweHeader::new();
+    header.set_content_type("JWT");
+    header.set_content_encryption(content_encryption.name());
+
+    let jwe = jwe::serialize_compact(jws.as_bytes(), &header, encrypter).map_err(encoding)?;
+    Ok(jwe)
+}
+
+/// returns a new unique token id (`jti`)
+pub(crate) fn new_jwt_id() -> String {
+    Uuid::new_v4().simple().to_string()
+}
+
 pub(crate) fn make_payload(
     issuer: &str,
     audiences: Vec<String>,
@@ -30,6 +452,7 @@ pub(crate) fn make_payload(
     let expiration_time: SystemTime = now_time + exp_time;
 
     let mut payload = JwtPayload::new();
+    payload.set_jwt_id(new_jwt_id());
     payload.set_issuer(issuer);
     payload.set_subject(subject);
     payload.set_audience(audiences);
@@ -40,61 +463,250 @@ pub(crate) fn make_payload(
     payload
 }
 
+/// decrypts a jwt with the key management algorithm of its header,
+/// the algorithm must be accepted by the policy
 pub(crate) fn decrypt_jwt(
     private_key: &Vec<u8>,
     jwt: &str,
-) -> Result<(JwtPayload, JweHeader), String> {
-    let decrypter = ECDH_ES
-        .decrypter_from_pem(private_key)
-        .map_err(|e| e.to_string())?;
-    let (payload, header) =
-        jwt::decode_with_decrypter(jwt, &decrypter).map_err(|e| e.to_string())?;
+    policy: &EncryptionPolicy,
+) -> Result<(JwtPayload, JweHeader), TokenError> {
+    let header = jwt::decode_header(jwt).map_err(malformed_header)?;
+    let algorithm = policy.check_key_management(header.as_ref())?;
+    let decrypter = algorithm.decrypter_from_pem(private_key, None)?;
+    decrypt_jwt_with(decrypter.as_ref(), jwt)
+}
+
+pub(crate) fn decrypt_jwt_with(
+    decrypter: &dyn JweDecrypter,
+    jwt: &str,
+) -> Result<(JwtPayload, JweHeader), TokenError> {
+    let (payload, header) = jwt::decode_with_decrypter(jwt, decrypter).map_err(decryption)?;
+    Ok((payload, header))
+}
+
+pub(crate) fn verify_jwt(
+    public_key: &Vec<u8>,
+    algorithm: SigningAlgorithm,
+    jwt: &str,
+) -> Result<(JwtPayload, JwsHeader), TokenError> {
+    let verifier = algorithm.verifier_from_pem(public_key)?;
+    verify_jwt_with(verifier.as_ref(), jwt)
+}
+
+pub(crate) fn verify_jwt_with(
+    verifier: &dyn JwsVerifier,
+    jwt: &str,
+) -> Result<(JwtPayload, JwsHeader), TokenError> {
+    let (payload, header) = jwt::decode_with_verifier(jwt, verifier).map_err(invalid_signature)?;
     Ok((payload, header))
 }
 
+/// returns the key id (`kid` header) of a jwt without decrypting it
+pub(crate) fn read_key_id(jwt: &str) -> Result<String, TokenError> {
+    let header = jwt::decode_header(jwt).map_err(malformed_header)?;
+    let kid = header
+        .claim("kid")
+        .ok_or_else(|| malformed_header("no JWT key id"))?;
+    let kid = kid
+        .as_str()
+        .ok_or_else(|| malformed_header("invalid JWT key id"))?;
+    Ok(kid.to_string())
+}
+
+/// decrypts a nested jwt and returns the signed jwt inside of it,
+/// the key management algorithm must be accepted by the policy
+pub(crate) fn decrypt_nested_jwt(
+    private_key: &Vec<u8>,
+    jwt: &str,
+    policy: &EncryptionPolicy,
+) -> Result<(String, JweHeader), TokenError> {
+    let header = jwt::decode_header(jwt).map_err(malformed_header)?;
+    let algorithm = policy.check_key_management(header.as_ref())?;
+    let decrypter = algorithm.decrypter_from_pem(private_key, None)?;
+    decrypt_nested_jwt_with(decrypter.as_ref(), jwt)
+}
+
+pub(crate) fn decrypt_nested_jwt_with(
+    decrypter: &dyn JweDecrypter,
+    jwt: &str,
+) -> Result<(String, JweHeader), TokenError> {
+    let (content, header) = jwe::deserialize_compact(jwt, decrypter).map_err(decryption)?;
+    let jws = String::from_utf8(content).map_err(decryption)?;
+    Ok((jws, header))
+}
+
 // validates the header of a jwt
-// checks if the type and the encryption are correct
-pub(crate) fn validate_header(header: &JweHeader) -> Result<(), String> {
-    let typ = header.claim("typ").ok_or("no JWT token type")?;
-    let enc = header.claim("enc").ok_or("no JWT token encryption")?;
+// checks if the type is correct and the algorithms are accepted by the policy
+pub(crate) fn validate_header(
+    header: &JweHeader,
+    policy: &EncryptionPolicy,
+) -> Result<(), TokenError> {
+    let typ = header
+        .claim("typ")
+        .ok_or_else(|| malformed_header("no JWT token type"))?;
     if typ != "JWT" {
-        return Err("invalid JWT token type".to_string());
+        return Err(malformed_header("invalid JWT token type"));
     }
-    if enc != "A128CBC-HS256" {
-        return Err("invalid JWT token encryption".to_string());
+    policy.check_key_management(header)?;
+    policy.check_content_encryption(header)?;
+    Ok(())
+}
+// validates the header of a signed jwt
+// checks if the type and the signing algorithm are correct
+pub(crate) fn validate_signed_header(
+    header: &JwsHeader,
+    algorithm: SigningAlgorithm,
+) -> Result<(), TokenError> {
+    let typ = header
+        .claim("typ")
+        .ok_or_else(|| malformed_header("no JWT token type"))?;
+    let alg = header
+        .claim("alg")
+        .ok_or_else(|| malformed_header("no JWT token algorithm"))?;
+    if typ != "JWT" {
+        return Err(malformed_header("invalid JWT token type"));
+    }
+    if alg != algorithm.name() {
+        return Err(malformed_header("invalid JWT token algorithm"));
+    }
+    Ok(())
+}
+
+// validates the header of a nested jwt
+// checks if the content type is correct and the algorithms are accepted by the policy
+pub(crate) fn validate_nested_header(
+    header: &JweHeader,
+    policy: &EncryptionPolicy,
+) -> Result<(), TokenError> {
+    let cty = header
+        .claim("cty")
+        .ok_or_else(|| malformed_header("no JWT token content type"))?;
+    if cty != "JWT" {
+        return Err(malformed_header("invalid JWT token content type"));
     }
+    policy.check_key_management(header)?;
+    policy.check_content_encryption(header)?;
     Ok(())
 }
+
 /// validates the payload of a jwt
 /// and returns the subject of the token
 /// if not returns an error
+///
+/// when a revocation check is given the token must have an id (`jti`)
+/// that was not revoked
 pub(crate) fn validate_payload(
     payload: &JwtPayload,
     expected_issuer: &str,
     accepted_audiences: &Vec<&str>,
-) -> Result<String, String> {
-    let issuer = payload.issuer().ok_or("no issuer")?;
-    let subject = payload.subject().ok_or("no subject")?;
-    let audiences = payload.audience().ok_or("no audience")?;
-    let issued_at = payload.issued_at().ok_or("no issued at")?;
-    let expires_at = payload.expires_at().ok_or("no expires at")?;
-    let not_before = payload.not_before().ok_or("no not before")?;
-    let time_now = SystemTime::now();
+    revocation_check: Option<&dyn RevocationCheck>,
+) -> Result<String, TokenError> {
+    let options = ValidationOptions::new(expected_issuer, accepted_audiences);
+    validate_payload_with_options(payload, &options, revocation_check)
+}
+
+/// validates the payload of a jwt with the given options
+/// and returns the subject of the token
+pub(crate) fn validate_payload_with_options(
+    payload: &JwtPayload,
+    options: &ValidationOptions,
+    revocation_check: Option<&dyn RevocationCheck>,
+) -> Result<String, TokenError> {
+    let issuer = payload.issuer().ok_or_else(|| missing_claim("iss"))?;
+    let subject = payload.subject().ok_or_else(|| missing_claim("sub"))?;
+    let audiences = payload.audience().ok_or_else(|| missing_claim("aud"))?;
+    let issued_at = payload.issued_at().ok_or_else(|| missing_claim("iat"))?;
+    let expires_at = payload.expires_at().ok_or_else(|| missing_claim("exp"))?;
+    let not_before = payload.not_before().ok_or_else(|| missing_claim("nbf"))?;
+    let time_now = options.now();
+    let leeway = options.leeway();
 
-    if issuer != expected_issuer {
-        return Err("invalid issuer".to_string());
+    if issuer != options.expected_issuer() {
+        return Err(TokenError::InvalidIssuer);
+    }
+    if !audiences.iter().any(|a| {
+        options
+            .accepted_audiences()
+            .iter()
+            .any(|accepted| accepted == a)
+    }) {
+        return Err(TokenError::InvalidAudience);
+    }
+    if issued_at > time_now + leeway {
+        return Err(TokenError::IssuedInFuture);
+    }
+    if expires_at + leeway < time_now {
+        return Err(TokenError::Expired);
+    }
+    if not_before > time_now + leeway {
+        return Err(TokenError::NotYetValid);
     }
-    if !audiences.iter().any(|a| accepted_audiences.contains(&a)) {
-        return Err("there is not an valid audience for this token".to_string());
+    if let Some(max_age) = options.max_age() {
+        if issued_at + max_age + leeway < time_now {
+            return Err(TokenError::TooOld);
+        }
     }
-    if issued_at > time_now {
-        return Err("token issued in the future".to_string());
+    for claim in options.required_claims() {
+        if payload.claim(claim).is_none() {
+            return Err(missing_claim(claim));
+        }
     }
-    if expires_at < time_now {
-        return Err("token expired".to_string());
+    for claim in options.forbidden_claims() {
+        if payload.claim(claim).is_some() {
+            return Err(TokenError::ForbiddenClaim(claim.to_string()));
+        }
     }
-    if not_before > time_now {
-        return Err("token not valid yet".to_string());
+    if let Some(realm) = options.realm() {
+        let user_data = UserData::from_subject(subject).map_err(TokenError::InvalidSubject)?;
+        if user_data.realm != realm {
+            return Err(TokenError::InvalidSubject(format!(
+                "unexpected realm {}",
+                user_data.realm
+            )));
+        }
+    }
+    if options.check_confirmation() {
+        // a bound token is only accepted with the proof of its key
+        // and on a connection with its certificate
+        let cnf = Confirmation::from_payload(payload)?;
+        if cnf.jkt.as_deref() != options.dpop_thumbprint() {
+            return Err(TokenError::InvalidProof(
+                "the token is not bound to the DPoP key".to_string(),
+            ));
+        }
+        if cnf.x5t_s256.as_deref() != options.certificate_thumbprint() {
+            return Err(TokenError::InvalidProof(
+                "the token is not bound to the client certificate".to_string(),
+            ));
+        }
+    }
+    if let Some(revocation_check) = revocation_check {
+        let jwt_id = payload.jwt_id().ok_or_else(|| missing_claim("jti"))?;
+        if revocation_check
+            .is_revoked(jwt_id)
+            .map_err(TokenError::RevocationCheck)?
+        {
+            return Err(TokenError::Revoked);
+        }
+    }
+    if let Some(resolver) = options.subject_resolver() {
+        let user_data = UserData::from_subject(subject).map_err(TokenError::InvalidSubject)?;
+        // the pairwise subject was derived for the audience the token is accepted for
+        let audience = audiences
+            .iter()
+            .find(|a| {
+                options
+                    .accepted_audiences()
+                    .iter()
+                    .any(|accepted| accepted == *a)
+            })
+            .ok_or(TokenError::InvalidAudience)?;
+        if let Some(resolved) = resolver
+            .resolve(&user_data, audience)
+            .map_err(TokenError::InvalidSubject)?
+        {
+            return Ok(resolved.get_subject());
+        }
     }
     // the application itself can validate the user data such
     // as realm, is alias, and the user id
@@ -107,8 +719,51 @@ pub(crate) fn validate_jwt(
     jwt: &str,
     expected_issuer: &str,
     accepted_audiences: &Vec<&str>,
-) -> Result<String, String> {
-    let (payload, header) = decrypt_jwt(private_key, jwt)?;
-    validate_header(&header)?;
-    validate_payload(&payload, expected_issuer, accepted_audiences)
+) -> Result<String, TokenError> {
+    let policy = EncryptionPolicy::default();
+    let (payload, header) = decrypt_jwt(private_key, jwt, &policy)?;
+    validate_header(&header, &policy)?;
+    validate_payload(&payload, expected_issuer, accepted_audiences, None)
+}
+
+/// verifies a signed jwt, or decrypts a nested jwt and verifies the signed jwt inside of it,
+/// with the keys of the key ring named in the `kid` headers
+/// and returns the payload and the purpose of the outer key,
+/// the claims of the payload are not validated
+///
+/// the signature is always required, anyone can encrypt a token with the public
+/// encryption keys so a token that is only encrypted is not authentic
+pub(crate) fn read_payload_with_key_ring(
+    key_ring: &KeyRing,
+    jwt: &str,
+) -> Result<(JwtPayload, KeyPurpose), TokenError> {
+    let key = key_ring.key_for_jwt(jwt)?;
+    let payload = match key.purpose() {
+        KeyPurpose::Encryption(algorithm) => {
+            let decrypter = key.decrypter()?;
+            let (jws, header) = decrypt_nested_jwt_with(decrypter.as_ref(), jwt)?;
+            let policy = key_ring.encryption_policy();
+            validate_nested_header(&header, &policy)?;
+            // the token must use the algorithm of the key named in its header
+            if policy.check_key_management(&header)? != algorithm {
+                return Err(malformed_header("invalid JWT token algorithm"));
+            }
+            let signing_key = key_ring.key_for_jwt(&jws)?;
+            verify_jwt_with_key(signing_key, &jws)?
+        }
+        KeyPurpose::Signing(_) => verify_jwt_with_key(key, jwt)?,
+    };
+    Ok((payload, key.purpose()))
+}
+
+// verifies a signed jwt with a signing key of the key ring
+fn verify_jwt_with_key(key: &RingKey, jws: &str) -> Result<JwtPayload, TokenError> {
+    let algorithm = match key.purpose() {
+        KeyPurpose::Signing(algorithm) => algorithm,
+        KeyPurpose::Encryption(_) => return Err(malformed_header("the token is not signed")),
+    };
+    let verifier = key.verifier()?;
+    let (payload, header) = verify_jwt_with(verifier.as_ref(), jws)?;
+    validate_signed_header(&header, algorithm)?;
+    Ok(payload)
 }
@@ -1,4 +1,4 @@
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct UserData {
     // an user id of at least 36 characters long (alphanumeric)
     // this should be used for the application to identify the user
@@ -0,0 +1,209 @@
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use crate::{pairwise::SubjectResolver, token_helper::EncryptionPolicy};
+
+/// the source of the current time used to validate the tokens
+pub trait Clock: Send + Sync {
+    fn now(&self) -> SystemTime;
+}
+
+/// the clock of the system
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// a clock that is stopped at the given time (for testing)
+pub struct FixedClock(pub SystemTime);
+
+impl Clock for FixedClock {
+    fn now(&self) -> SystemTime {
+        self.0
+    }
+}
+
+/// the rules applied to the claims of a token
+///
+/// by default the token must be issued by the expected issuer for one of the accepted
+/// audiences and be valid at the current system time without any tolerance,
+/// the encrypted tokens must use the default encryption algorithms
+/// and the tokens bound to a DPoP key (`cnf.jkt`) or to a client certificate (`cnf.x5t#S256`)
+/// are rejected unless the thumbprint of the key of a verified proof, or of the certificate,
+/// is given
+///
+/// # example
+/// ```
+/// use std::time::Duration;
+/// use token_helper::validation::ValidationOptions;
+///
+/// let options = ValidationOptions::new("test-issuer", &["test-audience"])
+///     .with_leeway(Duration::from_secs(30))
+///     .with_max_age(Duration::from_secs(86400))
+///     .require_claim("jti")
+///     .forbid_claim("act")
+///     .with_realm("test-realm");
+/// assert_eq!(options.leeway(), Duration::from_secs(30));
+/// ```
+#[derive(Clone)]
+pub struct ValidationOptions {
+    expected_issuer: String,
+    accepted_audiences: Vec<String>,
+    leeway: Duration,
+    clock: Arc<dyn Clock>,
+    max_age: Option<Duration>,
+    required_claims: Vec<String>,
+    forbidden_claims: Vec<String>,
+    realm: Option<String>,
+    encryption_policy: EncryptionPolicy,
+    subject_resolver: Option<Arc<dyn SubjectResolver>>,
+    dpop_thumbprint: Option<String>,
+    certificate_thumbprint: Option<String>,
+    check_confirmation: bool,
+}
+
+impl ValidationOptions {
+    pub fn new(expected_issuer: &str, accepted_audiences: &[&str]) -> Self {
+        Self {
+            expected_issuer: expected_issuer.to_string(),
+            accepted_audiences: accepted_audiences.iter().map(|a| a.to_string()).collect(),
+            leeway: Duration::ZERO,
+            clock: Arc::new(SystemClock),
+            max_age: None,
+            required_claims: Vec::new(),
+            forbidden_claims: Vec::new(),
+            realm: None,
+            encryption_policy: EncryptionPolicy::default(),
+            subject_resolver: None,
+            dpop_thumbprint: None,
+            certificate_thumbprint: None,
+            check_confirmation: true,
+        }
+    }
+
+    /// tolerance applied to the `iat`, `nbf` and `exp` claims (clock skew between nodes)
+    pub fn with_leeway(mut self, leeway: Duration) -> Self {
+        self.leeway = leeway;
+        self
+    }
+
+    /// replaces the system clock
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// rejects the tokens issued (`iat`) longer ago than the given age, even if not expired
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// the claim must be present in the token
+    pub fn require_claim(mut self, claim: &str) -> Self {
+        self.required_claims.push(claim.to_string());
+        self
+    }
+
+    /// the claim must not be present in the token
+    pub fn forbid_claim(mut self, claim: &str) -> Self {
+        self.forbidden_claims.push(claim.to_string());
+        self
+    }
+
+    /// the subject of the token must belong to the given realm
+    pub fn with_realm(mut self, realm: &str) -> Self {
+        self.realm = Some(realm.to_string());
+        self
+    }
+
+    /// the algorithms accepted in the header of the encrypted tokens
+    pub fn with_encryption_policy(mut self, policy: EncryptionPolicy) -> Self {
+        self.encryption_policy = policy;
+        self
+    }
+
+    /// maps the pairwise subjects back to the users,
+    /// the decoded user data is the one returned by the resolver
+    pub fn with_subject_resolver(mut self, resolver: Arc<dyn SubjectResolver>) -> Self {
+        self.subject_resolver = Some(resolver);
+        self
+    }
+
+    /// the token must be bound (`cnf.jkt`) to the key of the verified DPoP proof
+    pub fn with_dpop_thumbprint(mut self, jkt: &str) -> Self {
+        self.dpop_thumbprint = Some(jkt.to_string());
+        self
+    }
+
+    /// the token must be bound (`cnf.x5t#S256`) to the client certificate
+    /// presented on the TLS connection, see `mtls::certificate_thumbprint`
+    pub fn with_certificate_thumbprint(mut self, x5t_s256: &str) -> Self {
+        self.certificate_thumbprint = Some(x5t_s256.to_string());
+        self
+    }
+
+    /// accepts the bound tokens without checking their binding (`cnf`),
+    /// only for the callers that check it themselves, like the introspection
+    /// that returns the binding to the resource server
+    pub fn without_confirmation_check(mut self) -> Self {
+        self.check_confirmation = false;
+        self
+    }
+
+    pub fn expected_issuer(&self) -> &str {
+        &self.expected_issuer
+    }
+
+    pub fn accepted_audiences(&self) -> &[String] {
+        &self.accepted_audiences
+    }
+
+    pub fn leeway(&self) -> Duration {
+        self.leeway
+    }
+
+    pub fn now(&self) -> SystemTime {
+        self.clock.now()
+    }
+
+    pub fn max_age(&self) -> Option<Duration> {
+        self.max_age
+    }
+
+    pub fn required_claims(&self) -> &[String] {
+        &self.required_claims
+    }
+
+    pub fn forbidden_claims(&self) -> &[String] {
+        &self.forbidden_claims
+    }
+
+    pub fn realm(&self) -> Option<&str> {
+        self.realm.as_deref()
+    }
+    pub fn encryption_policy(&self) -> &EncryptionPolicy {
+        &self.encryption_policy
+    }
+
+    pub fn subject_resolver(&self) -> Option<&dyn SubjectResolver> {
+        self.subject_resolver.as_deref()
+    }
+
+    pub fn dpop_thumbprint(&self) -> Option<&str> {
+        self.dpop_thumbprint.as_deref()
+    }
+
+    pub fn certificate_thumbprint(&self) -> Option<&str> {
+        self.certificate_thumbprint.as_deref()
+    }
+
+    pub fn check_confirmation(&self) -> bool {
+        self.check_confirmation
+    }
+}
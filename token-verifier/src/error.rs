@@ -0,0 +1,54 @@
+use std::fmt;
+
+use token_helper::error::TokenError;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum VerificationError {
+    /// the request has no bearer (or DPoP) token in its `Authorization` header
+    MissingToken,
+    /// the bearer token was rejected
+    InvalidToken(TokenError),
+    /// the token could not be checked (the keys of the auth server could not be fetched)
+    Unavailable(String),
+}
+
+impl VerificationError {
+    /// returns the HTTP status of the error response
+    pub fn status_code(&self) -> u16 {
+        match self {
+            VerificationError::MissingToken | VerificationError::InvalidToken(_) => 401,
+            VerificationError::Unavailable(_) => 503,
+        }
+    }
+
+    /// returns the `WWW-Authenticate` header of the error response (RFC 6750 section 3)
+    pub fn www_authenticate(&self) -> Option<&'static str> {
+        match self {
+            VerificationError::MissingToken => Some("Bearer"),
+            // the token is bound to a DPoP key (RFC 9449 section 7.1)
+            VerificationError::InvalidToken(TokenError::InvalidProof(_)) => {
+                Some("DPoP error=\"invalid_dpop_proof\"")
+            }
+            VerificationError::InvalidToken(_) => Some("Bearer error=\"invalid_token\""),
+            VerificationError::Unavailable(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for VerificationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerificationError::MissingToken => write!(f, "no bearer token"),
+            VerificationError::InvalidToken(e) => write!(f, "invalid token: {}", e),
+            VerificationError::Unavailable(e) => write!(f, "the token can not be verified: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for VerificationError {}
+
+impl From<TokenError> for VerificationError {
+    fn from(e: TokenError) -> Self {
+        VerificationError::InvalidToken(e)
+    }
+}
@@ -0,0 +1,80 @@
+use rocket::{
+    http::Status,
+    request::{FromRequest, Outcome},
+    Request,
+};
+
+use token_helper::error::TokenError;
+
+use crate::{
+    error::VerificationError,
+    verifier::{Authenticated, DpopRequest, TokenVerifier},
+};
+
+/// the user of a request with a valid bearer token,
+/// the `TokenVerifier` must be managed by rocket
+///
+/// the DPoP proof of the `DPoP` header must be made for the uri built from
+/// the public url of the verifier, or from the `Host` header of the request
+///
+/// # example
+/// ```no_run
+/// use rocket::{get, launch, routes};
+/// use token_verifier::{
+///     jwks::JwksCache,
+///     verifier::{Authenticated, TokenVerifier, VerificationKey},
+/// };
+///
+/// #[get("/me")]
+/// fn me(user: Authenticated) -> String {
+///     user.user_data.user_id
+/// }
+///
+/// #[launch]
+/// fn rocket() -> _ {
+///     let jwks = JwksCache::new("https://auth.example.com/.well-known/jwks.json");
+///     rocket::build()
+///         .manage(TokenVerifier::new(VerificationKey::Jwks(jwks), "https://auth.example.com", &["billing"]))
+///         .mount("/", routes![me])
+/// }
+/// ```
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Authenticated {
+    type Error = VerificationError;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let verifier = match request.rocket().state::<TokenVerifier>() {
+            Some(verifier) => verifier,
+            None => {
+                let error = VerificationError::Unavailable("no token verifier".to_string());
+                return Outcome::Error((Status::InternalServerError, error));
+            }
+        };
+        let authorization = request.headers().get_one("Authorization");
+        let mut proofs = request.headers().get("DPoP");
+        let proof = match (proofs.next(), proofs.next()) {
+            (Some(_), Some(_)) => {
+                let error = VerificationError::InvalidToken(TokenError::InvalidProof(
+                    "only a single DPoP proof is allowed".to_string(),
+                ));
+                return Outcome::Error((Status::new(error.status_code()), error));
+            }
+            (proof, _) => proof,
+        };
+        let scheme = match request.rocket().config().tls_enabled() {
+            true => "https",
+            false => "http",
+        };
+        let host = request.host().map(|host| host.to_string());
+        let uri = verifier.request_uri(scheme, host.as_deref(), request.uri().path().as_str());
+        let method = request.method().as_str();
+        let dpop = match (proof, &uri) {
+            (Some(proof), Some(uri)) => Some(DpopRequest { proof, method, uri }),
+            _ => None,
+        };
+        match verifier.verify_authorization(authorization, dpop).await {
+            Ok(authenticated) => Outcome::Success(authenticated),
+            Err(e) => Outcome::Error((Status::new(e.status_code()), e)),
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use std::{
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+
+use josekit::jwk::JwkSet;
+
+/// the public keys published by the auth server (`/.well-known/jwks.json`),
+/// fetched on the first use and kept for `max_age`
+///
+/// the keys are fetched again before they expire when a token names an unknown key
+/// (a key rotation), at most once every `min_refresh_interval`
+pub struct JwksCache {
+    url: String,
+    client: reqwest::Client,
+    max_age: Duration,
+    min_refresh_interval: Duration,
+    cached: RwLock<Option<CachedKeySet>>,
+}
+
+struct CachedKeySet {
+    jwk_set: Arc<JwkSet>,
+    fetched_at: Instant,
+}
+
+impl JwksCache {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            client: reqwest::Client::new(),
+            // the auth server keeps the retired keys for longer than that
+            max_age: Duration::from_secs(600),
+            min_refresh_interval: Duration::from_secs(30),
+            cached: RwLock::new(None),
+        }
+    }
+
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    pub fn with_min_refresh_interval(mut self, interval: Duration) -> Self {
+        self.min_refresh_interval = interval;
+        self
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// returns the cached keys, they are fetched again when they are too old
+    /// but the old keys are still used if the auth server can not be reached
+    pub async fn key_set(&self) -> Result<Arc<JwkSet>, String> {
+        let cached = self.cached(self.max_age);
+        if let Some(jwk_set) = cached {
+            return Ok(jwk_set);
+        }
+        match self.fetch().await {
+            Ok(jwk_set) => Ok(jwk_set),
+            Err(e) => self.cached(Duration::MAX).ok_or(e),
+        }
+    }
+
+    /// fetches the keys again, unless they were fetched less than
+    /// `min_refresh_interval` ago (so the unknown keys can not flood the auth server)
+    pub async fn refresh(&self) -> Result<Arc<JwkSet>, String> {
+        match self.cached(self.min_refresh_interval) {
+            Some(jwk_set) => Ok(jwk_set),
+            None => self.fetch().await,
+        }
+    }
+
+    // returns the cached keys if they were fetched less than `max_age` ago
+    fn cached(&self, max_age: Duration) -> Option<Arc<JwkSet>> {
+        let cached = self.cached.read().unwrap_or_else(|e| e.into_inner());
+        cached
+            .as_ref()
+            .filter(|cached| cached.fetched_at.elapsed() < max_age)
+            .map(|cached| cached.jwk_set.clone())
+    }
+
+    async fn fetch(&self) -> Result<Arc<JwkSet>, String> {
+        let response = self
+            .client
+            .get(&self.url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| format!("could not fetch {}: {}", self.url, e))?;
+        let body = response
+            .bytes()
+            .await
+            .map_err(|e| format!("could not fetch {}: {}", self.url, e))?;
+        let jwk_set =
+            Arc::new(JwkSet::from_bytes(&body).map_err(|e| format!("invalid JWK Set: {}", e))?);
+        let mut cached = self.cached.write().unwrap_or_else(|e| e.into_inner());
+        *cached = Some(CachedKeySet {
+            jwk_set: jwk_set.clone(),
+            fetched_at: Instant::now(),
+        });
+        Ok(jwk_set)
+    }
+}
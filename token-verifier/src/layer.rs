@@ -0,0 +1,134 @@
+use std::{
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use futures_util::future::BoxFuture;
+use http::{
+    header::{AUTHORIZATION, HOST, WWW_AUTHENTICATE},
+    HeaderValue, Request, Response, StatusCode,
+};
+use token_helper::error::TokenError;
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::{
+    error::VerificationError,
+    verifier::{DpopRequest, TokenVerifier},
+};
+
+/// a middleware that rejects the requests without a valid bearer token
+/// and adds the `Authenticated` user to the extensions of the others
+///
+/// the DPoP proof of the `DPoP` header must be made for the uri built from
+/// the public url of the verifier, or from the uri and the `Host` header of the request
+///
+/// # example
+/// ```ignore
+/// let verifier = Arc::new(TokenVerifier::new(key, "https://auth.example.com", &["billing"]));
+/// let app = Router::new()
+///     .route("/me", get(|Extension(user): Extension<Authenticated>| async move {
+///         user.user_data.user_id
+///     }))
+///     .layer(AuthenticationLayer::new(verifier));
+/// ```
+#[derive(Clone)]
+pub struct AuthenticationLayer {
+    verifier: Arc<TokenVerifier>,
+}
+
+impl AuthenticationLayer {
+    pub fn new(verifier: Arc<TokenVerifier>) -> Self {
+        Self { verifier }
+    }
+}
+
+impl<S> Layer<S> for AuthenticationLayer {
+    type Service = Authentication<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Authentication {
+            inner,
+            verifier: self.verifier.clone(),
+        }
+    }
+}
+
+/// the service created by `AuthenticationLayer`
+#[derive(Clone)]
+pub struct Authentication<S> {
+    inner: S,
+    verifier: Arc<TokenVerifier>,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for Authentication<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>> + Clone + Send + 'static,
+    S::Future: Send,
+    ReqBody: Send + 'static,
+    ResBody: Default,
+{
+    type Response = Response<ResBody>;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<ReqBody>) -> Self::Future {
+        // the service that was polled ready handles the request
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let verifier = self.verifier.clone();
+        Box::pin(async move {
+            let headers = request.headers();
+            let authorization = headers
+                .get(AUTHORIZATION)
+                .and_then(|value| value.to_str().ok());
+            let mut proofs = headers.get_all("DPoP").iter();
+            let proof = match (proofs.next(), proofs.next()) {
+                (Some(_), Some(_)) => {
+                    return Ok(error_response(&VerificationError::InvalidToken(
+                        TokenError::InvalidProof("only a single DPoP proof is allowed".to_string()),
+                    )))
+                }
+                (proof, _) => proof.and_then(|value| value.to_str().ok()),
+            };
+            let uri = request.uri();
+            let host = uri
+                .authority()
+                .map(|authority| authority.as_str())
+                .or_else(|| headers.get(HOST).and_then(|value| value.to_str().ok()));
+            let uri = verifier.request_uri(uri.scheme_str().unwrap_or("http"), host, uri.path());
+            let dpop = match (proof, &uri) {
+                (Some(proof), Some(uri)) => Some(DpopRequest {
+                    proof,
+                    method: request.method().as_str(),
+                    uri,
+                }),
+                _ => None,
+            };
+            let result = verifier.verify_authorization(authorization, dpop).await;
+            match result {
+                Ok(authenticated) => {
+                    request.extensions_mut().insert(authenticated);
+                    inner.call(request).await
+                }
+                Err(e) => Ok(error_response(&e)),
+            }
+        })
+    }
+}
+
+fn error_response<B: Default>(error: &VerificationError) -> Response<B> {
+    let mut response = Response::new(B::default());
+    *response.status_mut() =
+        StatusCode::from_u16(error.status_code()).unwrap_or(StatusCode::UNAUTHORIZED);
+    if let Some(challenge) = error.www_authenticate() {
+        response
+            .headers_mut()
+            .insert(WWW_AUTHENTICATE, HeaderValue::from_static(challenge));
+    }
+    response
+}
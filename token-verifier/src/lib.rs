@@ -0,0 +1,16 @@
+//! verification of the bearer tokens issued by the auth server, for the relying parties
+//!
+//! the `rocket` feature adds a request guard for `Authenticated`
+//! and the `tower` feature a middleware that adds it to the request extensions
+
+pub mod error;
+pub mod jwks;
+pub mod verifier;
+
+#[cfg(feature = "rocket")]
+pub mod guard;
+#[cfg(feature = "tower")]
+pub mod layer;
+
+#[cfg(test)]
+mod tests;
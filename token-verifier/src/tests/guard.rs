@@ -0,0 +1,98 @@
+use rocket::{get, http::Header, http::Status, local::asynchronous::Client, routes};
+use token_helper::token_helper::SigningAlgorithm;
+
+use crate::{
+    jwks::JwksCache,
+    tests::{
+        bound_token, dpop_proof, sign, signing_key_ring, test_user, JwksServer, AUDIENCE,
+        ED25519_TEST_KEY, ISSUER,
+    },
+    verifier::{Authenticated, TokenVerifier, VerificationKey},
+};
+
+#[get("/me")]
+fn me(user: Authenticated) -> String {
+    user.user_data.user_id
+}
+
+#[tokio::test]
+async fn test_request_guard() {
+    let key_ring = signing_key_ring("ed", SigningAlgorithm::EdDSA, ED25519_TEST_KEY);
+    let server = JwksServer::start(&key_ring).await;
+    let verifier = TokenVerifier::new(
+        VerificationKey::Jwks(JwksCache::new(&server.url())),
+        ISSUER,
+        &[AUDIENCE],
+    );
+    let rocket = rocket::build().manage(verifier).mount("/", routes![me]);
+    let client = Client::untracked(rocket).await.unwrap();
+
+    let authorization = format!("Bearer {}", sign(&key_ring, AUDIENCE));
+    let response = client
+        .get("/me")
+        .header(Header::new("Authorization", authorization))
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.into_string().await.unwrap(), test_user().user_id);
+
+    let response = client.get("/me").dispatch().await;
+    assert_eq!(response.status(), Status::Unauthorized);
+    let authorization = format!("Bearer {}", sign(&key_ring, "other-audience"));
+    let response = client
+        .get("/me")
+        .header(Header::new("Authorization", authorization))
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Unauthorized);
+}
+
+#[tokio::test]
+async fn test_dpop_request_guard() {
+    let key_ring = signing_key_ring("ed", SigningAlgorithm::EdDSA, ED25519_TEST_KEY);
+    let server = JwksServer::start(&key_ring).await;
+    let verifier = TokenVerifier::new(
+        VerificationKey::Jwks(JwksCache::new(&server.url())),
+        ISSUER,
+        &[AUDIENCE],
+    )
+    .with_public_url("https://api.example.com");
+    let rocket = rocket::build().manage(verifier).mount("/", routes![me]);
+    let client = Client::untracked(rocket).await.unwrap();
+
+    let (client_key, token) = bound_token(&key_ring);
+    let authorization = format!("DPoP {}", token);
+    let proof = dpop_proof(&client_key, "GET", "https://api.example.com/me", &token);
+    let response = client
+        .get("/me")
+        .header(Header::new("Authorization", authorization.clone()))
+        .header(Header::new("DPoP", proof))
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.into_string().await.unwrap(), test_user().user_id);
+
+    // the proof must be made for the public url of the service
+    let proof = dpop_proof(&client_key, "GET", "https://other.example.com/me", &token);
+    let response = client
+        .get("/me")
+        .header(Header::new("Authorization", authorization))
+        .header(Header::new("DPoP", proof))
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Unauthorized);
+}
+
+#[tokio::test]
+async fn test_unmanaged_verifier() {
+    let key_ring = signing_key_ring("ed", SigningAlgorithm::EdDSA, ED25519_TEST_KEY);
+    let rocket = rocket::build().mount("/", routes![me]);
+    let client = Client::untracked(rocket).await.unwrap();
+    let authorization = format!("Bearer {}", sign(&key_ring, AUDIENCE));
+    let response = client
+        .get("/me")
+        .header(Header::new("Authorization", authorization))
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::InternalServerError);
+}
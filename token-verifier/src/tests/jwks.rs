@@ -0,0 +1,98 @@
+use std::time::Duration;
+
+use token_helper::{error::TokenError, key_ring::KeyPurpose, token_helper::SigningAlgorithm};
+
+use crate::{
+    error::VerificationError,
+    jwks::JwksCache,
+    tests::{
+        sign, signing_key_ring, test_user, JwksServer, AUDIENCE, ED25519_TEST_KEY, ISSUER,
+        P256_TEST_KEY,
+    },
+    verifier::{TokenVerifier, VerificationKey},
+};
+
+#[tokio::test]
+async fn test_cached_key_set() {
+    let key_ring = signing_key_ring("ed", SigningAlgorithm::EdDSA, ED25519_TEST_KEY);
+    let server = JwksServer::start(&key_ring).await;
+    let verifier = TokenVerifier::new(
+        VerificationKey::Jwks(JwksCache::new(&server.url())),
+        ISSUER,
+        &[AUDIENCE],
+    );
+    let token = sign(&key_ring, AUDIENCE);
+    for _ in 0..3 {
+        let authenticated = verifier.verify(&token).await.unwrap();
+        assert_eq!(authenticated.user_data, test_user());
+    }
+    // the keys are only fetched once
+    assert_eq!(server.request_count(), 1);
+    assert_eq!(
+        verifier
+            .verify(&sign(&key_ring, "other-audience"))
+            .await
+            .unwrap_err(),
+        VerificationError::InvalidToken(TokenError::InvalidAudience)
+    );
+}
+
+#[tokio::test]
+async fn test_key_rotation() {
+    let mut key_ring = signing_key_ring("ed", SigningAlgorithm::EdDSA, ED25519_TEST_KEY);
+    let server = JwksServer::start(&key_ring).await;
+    let jwks = JwksCache::new(&server.url()).with_min_refresh_interval(Duration::ZERO);
+    let verifier = TokenVerifier::new(VerificationKey::Jwks(jwks), ISSUER, &[AUDIENCE]);
+    assert!(verifier.verify(&sign(&key_ring, AUDIENCE)).await.is_ok());
+
+    key_ring
+        .rotate_key(
+            "ec",
+            KeyPurpose::Signing(SigningAlgorithm::ES256),
+            P256_TEST_KEY.to_vec(),
+        )
+        .unwrap();
+    server.publish(&key_ring);
+    // the new key is fetched when a token names it
+    assert!(verifier.verify(&sign(&key_ring, AUDIENCE)).await.is_ok());
+    assert_eq!(server.request_count(), 2);
+}
+
+#[tokio::test]
+async fn test_unknown_keys_are_throttled() {
+    let key_ring = signing_key_ring("ed", SigningAlgorithm::EdDSA, ED25519_TEST_KEY);
+    let server = JwksServer::start(&key_ring).await;
+    let verifier = TokenVerifier::new(
+        VerificationKey::Jwks(JwksCache::new(&server.url())),
+        ISSUER,
+        &[AUDIENCE],
+    );
+    let other_key_ring = signing_key_ring("other", SigningAlgorithm::ES256, P256_TEST_KEY);
+    for _ in 0..3 {
+        assert_eq!(
+            verifier
+                .verify(&sign(&other_key_ring, AUDIENCE))
+                .await
+                .unwrap_err(),
+            VerificationError::InvalidToken(TokenError::UnknownKey("other".to_string()))
+        );
+    }
+    assert_eq!(server.request_count(), 1);
+}
+
+#[tokio::test]
+async fn test_unreachable_auth_server() {
+    let key_ring = signing_key_ring("ed", SigningAlgorithm::EdDSA, ED25519_TEST_KEY);
+    // nothing listens on the discard port
+    let verifier = TokenVerifier::new(
+        VerificationKey::Jwks(JwksCache::new("http://127.0.0.1:9/.well-known/jwks.json")),
+        ISSUER,
+        &[AUDIENCE],
+    );
+    let error = verifier
+        .verify(&sign(&key_ring, AUDIENCE))
+        .await
+        .unwrap_err();
+    assert!(matches!(error, VerificationError::Unavailable(_)));
+    assert_eq!(error.status_code(), 503);
+}
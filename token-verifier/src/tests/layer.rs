@@ -0,0 +1,119 @@
+use std::{
+    convert::Infallible,
+    future::{ready, Ready},
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use http::{header::WWW_AUTHENTICATE, Request, Response, StatusCode};
+use token_helper::token_helper::SigningAlgorithm;
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::{
+    jwks::JwksCache,
+    layer::AuthenticationLayer,
+    tests::{
+        bound_token, dpop_proof, sign, signing_key_ring, test_user, JwksServer, AUDIENCE,
+        ED25519_TEST_KEY, ISSUER,
+    },
+    verifier::{Authenticated, TokenVerifier, VerificationKey},
+};
+
+/// a handler that returns the id of the authenticated user
+#[derive(Clone)]
+struct UserId;
+
+impl Service<Request<String>> for UserId {
+    type Response = Response<String>;
+    type Error = Infallible;
+    type Future = Ready<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: Request<String>) -> Self::Future {
+        let user_id = request
+            .extensions()
+            .get::<Authenticated>()
+            .map(|authenticated| authenticated.user_data.user_id.clone())
+            .unwrap_or_default();
+        ready(Ok(Response::new(user_id)))
+    }
+}
+
+fn request(authorization: Option<String>) -> Request<String> {
+    let mut request = Request::builder().uri("/me");
+    if let Some(authorization) = authorization {
+        request = request.header("Authorization", authorization);
+    }
+    request.body(String::new()).unwrap()
+}
+
+#[tokio::test]
+async fn test_authentication_layer() {
+    let key_ring = signing_key_ring("ed", SigningAlgorithm::EdDSA, ED25519_TEST_KEY);
+    let server = JwksServer::start(&key_ring).await;
+    let verifier = TokenVerifier::new(
+        VerificationKey::Jwks(JwksCache::new(&server.url())),
+        ISSUER,
+        &[AUDIENCE],
+    );
+    let mut service = AuthenticationLayer::new(Arc::new(verifier)).layer(UserId);
+
+    let authorization = format!("Bearer {}", sign(&key_ring, AUDIENCE));
+    let response = service.call(request(Some(authorization))).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.body(), &test_user().user_id);
+
+    // the handler is not called without a valid token
+    let response = service.call(request(None)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(response.headers()[WWW_AUTHENTICATE], "Bearer");
+    assert!(response.body().is_empty());
+    let authorization = format!("Bearer {}", sign(&key_ring, "other-audience"));
+    let response = service.call(request(Some(authorization))).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        response.headers()[WWW_AUTHENTICATE],
+        "Bearer error=\"invalid_token\""
+    );
+}
+
+#[tokio::test]
+async fn test_dpop_authentication_layer() {
+    let key_ring = signing_key_ring("ed", SigningAlgorithm::EdDSA, ED25519_TEST_KEY);
+    let server = JwksServer::start(&key_ring).await;
+    let verifier = TokenVerifier::new(
+        VerificationKey::Jwks(JwksCache::new(&server.url())),
+        ISSUER,
+        &[AUDIENCE],
+    );
+    let mut service = AuthenticationLayer::new(Arc::new(verifier)).layer(UserId);
+
+    // the proof is made for the uri of the `Host` header
+    let (client_key, token) = bound_token(&key_ring);
+    let proof = dpop_proof(&client_key, "GET", "http://api.example.com/me", &token);
+    let request = Request::builder()
+        .uri("/me")
+        .header("Host", "api.example.com")
+        .header("Authorization", format!("DPoP {}", token))
+        .header("DPoP", proof)
+        .body(String::new())
+        .unwrap();
+    let response = service.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.body(), &test_user().user_id);
+
+    // the bound token is rejected as a bearer token
+    let response = service
+        .call(self::request(Some(format!("Bearer {}", token))))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        response.headers()[WWW_AUTHENTICATE],
+        "DPoP error=\"invalid_dpop_proof\""
+    );
+}
@@ -0,0 +1,235 @@
+use std::{sync::Arc, time::Duration};
+
+use josekit::jwk::JwkSet;
+use token_helper::{
+    claims::{CustomClaims, TokenClaims},
+    dpop::{DpopVerifier, MemoryReplayCache},
+    error::TokenError,
+    manager::{decode_token, DecodingKey},
+    token_helper::SigningAlgorithm,
+    user::UserData,
+    validation::ValidationOptions,
+};
+
+use crate::{error::VerificationError, jwks::JwksCache};
+
+/// the key used to check the tokens
+pub enum VerificationKey {
+    /// the public key of the auth server (PEM), for the signed tokens
+    Signing(Vec<u8>, SigningAlgorithm),
+    /// the public keys published by the auth server, for the tokens it signs
+    Jwks(JwksCache),
+    /// the private key of the service and the public key of the auth server (PEM),
+    /// for the tokens signed by the auth server and then encrypted for the service
+    Nested(Vec<u8>, Vec<u8>, SigningAlgorithm),
+    /// the private key of the service (PEM) and the public keys published by the auth server,
+    /// for the tokens signed by the auth server and then encrypted for the service
+    NestedJwks(Vec<u8>, JwksCache),
+}
+
+/// the DPoP proof (RFC 9449) sent in the `DPoP` header of a request,
+/// with the method and the uri of the request it must be made for
+pub struct DpopRequest<'r> {
+    pub proof: &'r str,
+    pub method: &'r str,
+    pub uri: &'r str,
+}
+
+/// the user and the claims of a verified bearer token
+#[derive(Debug, Clone, PartialEq)]
+pub struct Authenticated {
+    pub user_data: UserData,
+    pub claims: CustomClaims,
+}
+
+/// checks the bearer tokens of the requests with the same rules as
+/// `token_helper::manager::decode_user_data` (issuer, audiences and times)
+///
+/// the tokens bound to a DPoP key must be sent with the `DPoP` scheme and a proof
+/// of their key, the proofs already used are remembered by this instance only,
+/// the tokens bound to a client certificate are rejected
+///
+/// # example
+/// ```no_run
+/// use token_verifier::{
+///     jwks::JwksCache,
+///     verifier::{TokenVerifier, VerificationKey},
+/// };
+///
+/// let verifier = TokenVerifier::new(
+///     VerificationKey::Jwks(JwksCache::new("https://auth.example.com/.well-known/jwks.json")),
+///     "https://auth.example.com",
+///     &["billing"],
+/// );
+/// ```
+pub struct TokenVerifier {
+    key: VerificationKey,
+    issuer: String,
+    audiences: Vec<String>,
+    leeway: Duration,
+    dpop_verifier: DpopVerifier,
+    public_url: Option<String>,
+}
+
+impl TokenVerifier {
+    pub fn new(key: VerificationKey, issuer: &str, audiences: &[&str]) -> Self {
+        Self {
+            key,
+            issuer: issuer.to_string(),
+            audiences: audiences.iter().map(|a| a.to_string()).collect(),
+            leeway: Duration::ZERO,
+            dpop_verifier: DpopVerifier::new(Arc::new(MemoryReplayCache::new())),
+            public_url: None,
+        }
+    }
+
+    /// accepts the tokens expired (or not valid yet) for less than the leeway
+    pub fn with_leeway(mut self, leeway: Duration) -> Self {
+        self.leeway = leeway;
+        self
+    }
+
+    /// replaces the verifier of the DPoP proofs (to share its replay cache for example)
+    pub fn with_dpop_verifier(mut self, dpop_verifier: DpopVerifier) -> Self {
+        self.dpop_verifier = dpop_verifier;
+        self
+    }
+
+    /// the address of the service as seen by the clients (behind a proxy),
+    /// the DPoP proofs must be made for it, by default it is read from the `Host` header
+    pub fn with_public_url(mut self, public_url: &str) -> Self {
+        self.public_url = Some(public_url.trim_end_matches('/').to_string());
+        self
+    }
+
+    /// returns the uri the DPoP proofs of a request must be made for
+    pub fn request_uri(&self, scheme: &str, host: Option<&str>, path: &str) -> Option<String> {
+        match &self.public_url {
+            Some(public_url) => Some(format!("{}{}", public_url, path)),
+            None => Some(format!("{}://{}{}", scheme, host?, path)),
+        }
+    }
+
+    /// verifies the token of an `Authorization: Bearer <token>` header,
+    /// or of an `Authorization: DPoP <token>` header with the DPoP proof of the request
+    pub async fn verify_authorization(
+        &self,
+        authorization: Option<&str>,
+        dpop: Option<DpopRequest<'_>>,
+    ) -> Result<Authenticated, VerificationError> {
+        let (scheme, token) = authorization
+            .and_then(authorization_token)
+            .ok_or(VerificationError::MissingToken)?;
+        let mut options = self.validation_options();
+        if scheme == Scheme::Dpop {
+            let dpop = dpop.ok_or_else(|| {
+                TokenError::InvalidProof("the request has no DPoP proof".to_string())
+            })?;
+            let proof =
+                self.dpop_verifier
+                    .verify(dpop.proof, dpop.method, dpop.uri, Some(token))?;
+            options = options.with_dpop_thumbprint(&proof.jkt);
+        }
+        self.verify_with_options(token, &options).await
+    }
+
+    /// verifies a token and returns its user and its claims,
+    /// the tokens bound to a DPoP key are rejected
+    pub async fn verify(&self, token: &str) -> Result<Authenticated, VerificationError> {
+        self.verify_with_options(token, &self.validation_options())
+            .await
+    }
+
+    fn validation_options(&self) -> ValidationOptions {
+        let audiences: Vec<&str> = self.audiences.iter().map(String::as_str).collect();
+        ValidationOptions::new(&self.issuer, &audiences).with_leeway(self.leeway)
+    }
+
+    async fn verify_with_options(
+        &self,
+        token: &str,
+        options: &ValidationOptions,
+    ) -> Result<Authenticated, VerificationError> {
+        let claims = match &self.key {
+            VerificationKey::Signing(public_key, algorithm) => {
+                let key = DecodingKey::Verify {
+                    public_key: public_key.clone(),
+                    algorithm: *algorithm,
+                };
+                decode_token(&key, token, options, None)?
+            }
+            VerificationKey::Jwks(jwks) => decode_with_jwks(jwks, None, token, options).await?,
+            VerificationKey::Nested(private_key, public_key, algorithm) => {
+                let key = DecodingKey::Nested {
+                    private_key: private_key.clone(),
+                    public_key: public_key.clone(),
+                    algorithm: *algorithm,
+                };
+                decode_token(&key, token, options, None)?
+            }
+            VerificationKey::NestedJwks(private_key, jwks) => {
+                decode_with_jwks(jwks, Some(private_key), token, options).await?
+            }
+        };
+        Ok(Authenticated {
+            user_data: claims.user_data,
+            claims: claims.custom,
+        })
+    }
+}
+
+// decodes a token with the published keys, decrypting it first when there is a private key
+async fn decode_with_jwks(
+    jwks: &JwksCache,
+    private_key: Option<&Vec<u8>>,
+    token: &str,
+    options: &ValidationOptions,
+) -> Result<TokenClaims, VerificationError> {
+    let decode = |jwk_set: &JwkSet| {
+        let key = match private_key {
+            Some(private_key) => DecodingKey::NestedJwkSet {
+                private_key: private_key.clone(),
+                jwk_set,
+            },
+            None => DecodingKey::JwkSet(jwk_set),
+        };
+        decode_token(&key, token, options, None)
+    };
+    let jwk_set = jwks
+        .key_set()
+        .await
+        .map_err(VerificationError::Unavailable)?;
+    match decode(&jwk_set) {
+        // the key may have been rotated since the keys were fetched
+        Err(TokenError::UnknownKey(_)) => {
+            let jwk_set = jwks
+                .refresh()
+                .await
+                .map_err(VerificationError::Unavailable)?;
+            Ok(decode(&jwk_set)?)
+        }
+        result => Ok(result?),
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Scheme {
+    Bearer,
+    Dpop,
+}
+
+// the scheme is case insensitive (RFC 7235 section 2.1)
+fn authorization_token(authorization: &str) -> Option<(Scheme, &str)> {
+    let (scheme, token) = authorization.split_once(' ')?;
+    let token = token.trim();
+    if token.is_empty() {
+        return None;
+    }
+    if scheme.eq_ignore_ascii_case("Bearer") {
+        Some((Scheme::Bearer, token))
+    } else if scheme.eq_ignore_ascii_case("DPoP") {
+        Some((Scheme::Dpop, token))
+    } else {
+        None
+    }
+}